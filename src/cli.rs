@@ -0,0 +1,148 @@
+use std::sync::mpsc;
+use std::thread;
+
+use eframe::egui;
+
+use crate::logging::Level;
+use crate::mover::{self, MoveSettings, Plan, Reporter, WorkerMessage};
+
+pub const USAGE: &str = "Usage: Move_Files [--portable] [--profile <name> [--run]]
+
+  --portable        Keep settings, profiles and history in a \"data\" folder
+                    next to the program, like a portable.flag file there.
+  --profile <name>  Open with the settings of a saved profile.
+  --run             Move the files of the profile right away without
+                    opening the window, e.g. from Task Scheduler or cron.";
+
+/// Command line options.
+#[derive(Default)]
+pub struct Args {
+    pub profile: Option<String>,
+    pub run: bool,
+    pub portable: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--profile" => {
+                    let name = args.next().ok_or("--profile needs a profile name")?;
+                    parsed.profile = Some(name);
+                }
+                "--run" => parsed.run = true,
+                "--portable" => parsed.portable = true,
+                _ => return Err(format!("unknown option \"{}\"", arg)),
+            }
+        }
+        if parsed.run && parsed.profile.is_none() {
+            return Err("--run needs a --profile to run".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Scans and moves like "Move Files" followed by confirming, printing the log
+/// instead of showing it. Returns false if anything failed.
+pub fn run(settings: MoveSettings) -> bool {
+    // Profiles never store passwords, and there is nobody to ask for one.
+    if let Some(credentials) = &settings.share_login {
+        if !credentials.username.trim().is_empty() && credentials.password.is_empty() {
+            eprintln!(
+                "The profile logs into shares as \"{}\", but passwords are not saved in \
+                 profiles. Leave the username empty to use the credentials stored in \
+                 Windows, e.g. in the Credential Manager.",
+                credentials.username.trim()
+            );
+            return false;
+        }
+    }
+    let (plan, logged_error, scanned) = run_worker(move |reporter| {
+        mover::scan_thread(settings, reporter).map_err(|e| e.to_string())
+    });
+    let scan_failed = match scanned {
+        Some(Ok(())) => logged_error,
+        Some(Err(e)) => {
+            if !logged_error {
+                eprintln!("Scan failed: {}", e);
+            }
+            true
+        }
+        // The scan panicked.
+        None => true,
+    };
+    let Some(plan) = plan else {
+        return false;
+    };
+    let (_, move_failed, moved) = run_worker(move |reporter| {
+        mover::execute_thread(plan, None, None, reporter);
+    });
+    !scan_failed && !move_failed && moved.is_some()
+}
+
+/// Runs `work` on a worker thread and prints its log as it comes in.
+/// Returns the plan it sent, if any, whether it logged errors, and what it
+/// returned, or None if it panicked.
+fn run_worker<T: Send + 'static>(
+    work: impl FnOnce(Reporter) -> T + Send + 'static,
+) -> (Option<Plan>, bool, Option<T>) {
+    let (tx, rx) = mpsc::channel();
+    // Without a window there is nothing to repaint; the context is unused.
+    let reporter = Reporter::new(tx, egui::Context::default());
+    let worker = thread::spawn(move || work(reporter));
+    let mut plan = None;
+    let mut failed = false;
+    // Ends once the worker is done and has dropped its reporter.
+    for message in rx {
+        match message {
+            WorkerMessage::Log(entry) => {
+                failed |= entry.level == Level::Error;
+                println!("{}", entry);
+            }
+            WorkerMessage::Plan(scanned) => plan = Some(scanned),
+            _ => {}
+        }
+    }
+    (plan, failed, worker.join().ok())
+}
+
+/// The app is built for the Windows GUI subsystem and has no console of its
+/// own; attaching to the one it was started from makes the output visible.
+#[cfg(windows)]
+pub fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: takes no pointers; fails harmlessly without a parent console.
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+pub fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let args = parse(&["--portable", "--profile", "Photos", "--run"]).unwrap();
+        assert_eq!(args.profile.as_deref(), Some("Photos"));
+        assert!(args.run && args.portable);
+
+        let args = parse(&[]).unwrap();
+        assert!(args.profile.is_none() && !args.run && !args.portable);
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(parse(&["--profile"]).is_err());
+        assert!(parse(&["--run"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::hashing::HashCache;
+use crate::logging::LogEntry;
+use crate::mover::{self, Plan, PlannedMove, Reporter, WorkerMessage};
+use crate::protected;
+
+/// A file that is not the same on both sides.
+pub struct Difference {
+    /// Path relative to both compared folders.
+    pub relative: PathBuf,
+    /// Size on the left, `None` if the file only exists on the right.
+    pub left_size: Option<u64>,
+    /// Size on the right, `None` if the file only exists on the left.
+    pub right_size: Option<u64>,
+}
+
+impl Difference {
+    /// Present on both sides, with different content.
+    pub fn is_changed(&self) -> bool {
+        self.left_size.is_some() && self.right_size.is_some()
+    }
+}
+
+/// The result of comparing two folders.
+pub struct Comparison {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    /// Sorted by path.
+    pub differences: Vec<Difference>,
+}
+
+impl Comparison {
+    pub fn only_left(&self) -> usize {
+        self.differences
+            .iter()
+            .filter(|d| d.right_size.is_none())
+            .count()
+    }
+
+    pub fn only_right(&self) -> usize {
+        self.differences
+            .iter()
+            .filter(|d| d.left_size.is_none())
+            .count()
+    }
+
+    pub fn changed(&self) -> usize {
+        self.differences.iter().filter(|d| d.is_changed()).count()
+    }
+
+    /// A plan that brings the files missing or different on the other side
+    /// over from the left (`to_right`) or from the right. Files that differ
+    /// keep both versions: the incoming one gets a numbered name.
+    pub fn plan(&self, to_right: bool, copy: bool) -> Plan {
+        let (from, to) = if to_right {
+            (&self.left, &self.right)
+        } else {
+            (&self.right, &self.left)
+        };
+        let moves = self
+            .differences
+            .iter()
+            .filter_map(|d| {
+                let size = if to_right { d.left_size } else { d.right_size }?;
+                // Where the file differs, the existing one is found at the
+                // destination when moving and the numbered name is picked then.
+                Some(PlannedMove {
+                    source: from.join(&d.relative),
+                    destination: to.join(&d.relative),
+                    size,
+                    collision: d.is_changed(),
+                    rule: None,
+                    owner: None,
+                })
+            })
+            .collect();
+        Plan {
+            input_path: from.display().to_string(),
+            output_path: to.display().to_string(),
+            moves,
+            duplicates: Vec::new(),
+            copy,
+            rules: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Every file under `dir` with its size, keyed by the path relative to `dir`.
+fn list_files(dir: &Path) -> BTreeMap<PathBuf, u64> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let size = e.metadata().ok()?.len();
+            let relative = e.path().strip_prefix(dir).ok()?.to_path_buf();
+            Some((relative, size))
+        })
+        .collect()
+}
+
+/// This function runs in a background thread. It compares the files under
+/// `left` and `right` by relative path, then by size and finally by content
+/// hash, and sends the differences back via the provided reporter.
+pub fn compare_thread(
+    left: PathBuf,
+    right: PathBuf,
+    protected_paths: String,
+    reporter: Reporter,
+) -> Result<(), Box<dyn Error>> {
+    for dir in [&left, &right] {
+        if !dir.is_dir() {
+            reporter.log(LogEntry::error(format!(
+                "{} is not a valid directory.",
+                dir.display()
+            )));
+            return Err(format!("{} is not a directory.", dir.display()).into());
+        }
+        // Either side may become the source of a move.
+        if let Some(entry) = protected::blocking_entry(dir, &protected_paths, true) {
+            reporter.log(LogEntry::error(format!(
+                "Refusing to compare {}: it is protected by \"{}\". \
+                     Edit the protected paths list to allow it.",
+                dir.display(),
+                entry
+            )));
+            return Err(format!("{} is a protected location.", dir.display()).into());
+        }
+    }
+
+    let left_files = list_files(&left);
+    let right_files = list_files(&right);
+
+    // Files of equal size on both sides need their content compared. Hash
+    // them all in parallel first.
+    let same_size: Vec<&PathBuf> = left_files
+        .iter()
+        .filter(|(relative, size)| right_files.get(*relative) == Some(size))
+        .map(|(relative, _)| relative)
+        .collect();
+    let mut cache = HashCache::load();
+    let to_hash: Vec<PathBuf> = same_size
+        .iter()
+        .flat_map(|relative| [left.join(relative), right.join(relative)])
+        .collect();
+    cache.hash_all(&to_hash);
+
+    let mut differences = Vec::new();
+    for (relative, &left_size) in &left_files {
+        let right_size = right_files.get(relative).copied();
+        let differs = match right_size {
+            None => true,
+            Some(right_size) if right_size != left_size => true,
+            Some(_) => {
+                match mover::is_duplicate(&mut cache, &left.join(relative), &right.join(relative)) {
+                    Ok(identical) => !identical,
+                    // Listed rather than hidden, as it may well differ.
+                    Err(e) => {
+                        reporter.log(LogEntry::warning(format!(
+                            "Could not compare {}, listed as changed: {}",
+                            relative.display(),
+                            e
+                        )));
+                        true
+                    }
+                }
+            }
+        };
+        if differs {
+            differences.push(Difference {
+                relative: relative.clone(),
+                left_size: Some(left_size),
+                right_size,
+            });
+        }
+    }
+    for (relative, &right_size) in &right_files {
+        if !left_files.contains_key(relative) {
+            differences.push(Difference {
+                relative: relative.clone(),
+                left_size: None,
+                right_size: Some(right_size),
+            });
+        }
+    }
+    differences.sort_by(|a, b| a.relative.cmp(&b.relative));
+    if let Err(e) = cache.save() {
+        reporter.log(LogEntry::warning(format!(
+            "Could not save the hash cache: {}",
+            e
+        )));
+    }
+
+    let comparison = Comparison {
+        left,
+        right,
+        differences,
+    };
+    reporter.log(LogEntry::info(format!(
+        "Compared {} and {}: {} only on the left, {} only on the right, {} different.",
+        comparison.left.display(),
+        comparison.right.display(),
+        comparison.only_left(),
+        comparison.only_right(),
+        comparison.changed()
+    )));
+    reporter.send(WorkerMessage::Comparison(comparison));
+    Ok(())
+}
@@ -0,0 +1,381 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::hashing;
+
+/// How long to wait before copying a file again that changed while it was
+/// being copied, giving the program writing it time to finish.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Copies a file with its contents, permissions and metadata streams.
+/// Returns what could not be carried over, so the caller can warn about it.
+///
+/// On Windows `fs::copy` uses `CopyFileExW`, which already copies alternate
+/// data streams and extended attributes, and on macOS `fcopyfile`, which
+/// copies extended attributes too. Elsewhere, and for sparse copies,
+/// `fs::copy` only copies the contents and permissions, so extended
+/// attributes are copied one by one on Unix.
+/// Sparse files stay sparse where the OS can report their holes.
+///
+/// The data is hashed as it is read, and the finished copy read back and
+/// checked against that hash, so a copy the destination drive mangled is
+/// caught. Clones share the original's data and aren't checked.
+///
+/// If the file changes while it is copied, most likely because another program
+/// is still writing it, it is copied once more after a pause. Should it keep
+/// changing, or the copy fail, the partial copy is removed and an error
+/// returned, rather than keeping a truncated file.
+///
+/// The destination must not exist yet. It is created up front, so a file
+/// another program creates there in the meantime is never overwritten or
+/// removed. The data goes into a temporary file next to it, which replaces
+/// the destination once complete.
+pub fn copy_file(source: &Path, destination: &Path) -> io::Result<Vec<String>> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(destination)?;
+    let result = partial_path(destination).and_then(|partial| {
+        let result = copy_unchanged(source, &partial)
+            .and_then(|lost| fs::rename(&partial, destination).map(|()| lost));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        result
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+    result
+}
+
+/// A free name for the temporary file a copy is written to before it
+/// replaces `destination`.
+fn partial_path(destination: &Path) -> io::Result<PathBuf> {
+    let name = destination
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?
+        .to_string_lossy();
+    (0..)
+        .map(|n| {
+            destination.with_file_name(format!(".{}.{}.{}.partial", name, std::process::id(), n))
+        })
+        .find(|path| !path.exists())
+        .ok_or_else(|| io::Error::other("no free temporary file name"))
+}
+
+fn copy_unchanged(source: &Path, destination: &Path) -> io::Result<Vec<String>> {
+    for attempt in 0..2 {
+        if attempt > 0 {
+            thread::sleep(RETRY_DELAY);
+            // Reported as the file changing below, which is what went wrong.
+            if fs::remove_file(destination).is_err() {
+                break;
+            }
+        }
+        let before = snapshot(source)?;
+        let copied = copy_once(source, destination);
+        // A file shrinking while it is read can also make the copy fail, so
+        // a change is retried whether or not the copy went through.
+        if snapshot(source)? == before {
+            return copied;
+        }
+    }
+    Err(io::Error::other(
+        "the file kept changing while being copied, it may still be in use",
+    ))
+}
+
+/// Size and modification time, to tell whether a file was written to.
+fn snapshot(path: &Path) -> io::Result<(u64, Option<SystemTime>)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.len(), metadata.modified().ok()))
+}
+
+fn copy_once(source: &Path, destination: &Path) -> io::Result<Vec<String>> {
+    // A clone (reflink) shares the data with the original until either is
+    // changed, so it is instant and takes no space. Only copy-on-write file
+    // systems (Btrfs, XFS, APFS, ReFS) can do it, and only within a volume.
+    if reflink_copy::reflink(source, destination).is_ok() {
+        fs::set_permissions(destination, fs::metadata(source)?.permissions())?;
+        return Ok(copy_xattrs(source, destination));
+    }
+    let hash = match is_sparse(source).then(|| copy_sparse(source, destination)) {
+        Some(Ok(hash)) => hash,
+        // If holes can't be found after all, a plain copy still works; it
+        // just writes the holes out as zeroes.
+        _ => copy_contents(source, destination)?,
+    };
+    if hashing::hash_file(destination)? != hash {
+        return Err(io::Error::other(
+            "the copy does not match the original, the destination drive may be failing",
+        ));
+    }
+    Ok(copy_xattrs(source, destination))
+}
+
+/// Hands everything read through to a hasher, so the copy can be checked
+/// against exactly the data that was read.
+struct HashingReader<'a, R> {
+    inner: R,
+    hasher: &'a mut blake3::Hasher,
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Copies the contents and permissions and returns the hash of the data read.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn copy_contents(source: &Path, destination: &Path) -> io::Result<String> {
+    let input = File::open(source)?;
+    let permissions = input.metadata()?.permissions();
+    let mut output = File::create(destination)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut reader = HashingReader {
+        inner: input,
+        hasher: &mut hasher,
+    };
+    io::copy(&mut reader, &mut output)?;
+    output.set_permissions(permissions)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// `fs::copy` is kept here for the streams and attributes it copies, so the
+/// original is hashed after the copy instead of while it is read.
+#[cfg(any(windows, target_os = "macos"))]
+fn copy_contents(source: &Path, destination: &Path) -> io::Result<String> {
+    fs::copy(source, destination)?;
+    hashing::hash_file(source)
+}
+
+/// Feeds `len` zero bytes to the hasher, standing in for a hole.
+fn hash_zeros(hasher: &mut blake3::Hasher, mut len: u64) {
+    static ZEROS: [u8; 64 * 1024] = [0; 64 * 1024];
+    while len > 0 {
+        let n = len.min(ZEROS.len() as u64);
+        hasher.update(&ZEROS[..n as usize]);
+        len -= n;
+    }
+}
+
+/// True if the file takes up less space on disk than its length, meaning
+/// parts of it are holes that read as zeroes (VM images, databases).
+#[cfg(unix)]
+fn is_sparse(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    // st_blocks is always counted in 512-byte units.
+    fs::metadata(path).is_ok_and(|m| m.blocks().saturating_mul(512) < m.len())
+}
+
+#[cfg(not(unix))]
+fn is_sparse(_path: &Path) -> bool {
+    false
+}
+
+/// Copies only the data regions of a sparse file, found with SEEK_DATA and
+/// SEEK_HOLE, and leaves the holes in between unwritten. Returns the hash of
+/// the file as read, holes included.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+))]
+fn copy_sparse(source: &Path, destination: &Path) -> io::Result<String> {
+    use std::io::{Seek, SeekFrom};
+    use std::os::unix::io::AsRawFd;
+
+    let mut input = File::open(source)?;
+    let metadata = input.metadata()?;
+    let mut output = File::create(destination)?;
+    // Sized up front, so a trailing hole is kept too.
+    output.set_len(metadata.len())?;
+    let fd = input.as_raw_fd();
+    let mut hasher = blake3::Hasher::new();
+    let mut offset = 0;
+    while offset < metadata.len() as libc::off_t {
+        // SAFETY: lseek only moves the file offset of a descriptor we own.
+        let data_start = unsafe { libc::lseek(fd, offset, libc::SEEK_DATA) };
+        if data_start < 0 {
+            let e = io::Error::last_os_error();
+            // ENXIO: only a hole is left until the end of the file.
+            if e.raw_os_error() == Some(libc::ENXIO) {
+                break;
+            }
+            return Err(e);
+        }
+        // SAFETY: as above.
+        let data_end = unsafe { libc::lseek(fd, data_start, libc::SEEK_HOLE) };
+        if data_end < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        hash_zeros(&mut hasher, (data_start - offset) as u64);
+        input.seek(SeekFrom::Start(data_start as u64))?;
+        output.seek(SeekFrom::Start(data_start as u64))?;
+        let copied = io::copy(
+            &mut HashingReader {
+                inner: (&mut input).take((data_end - data_start) as u64),
+                hasher: &mut hasher,
+            },
+            &mut output,
+        )?;
+        if copied < (data_end - data_start) as u64 {
+            return Err(io::Error::other("the file got shorter while being copied"));
+        }
+        offset = data_end;
+    }
+    hash_zeros(&mut hasher, metadata.len().saturating_sub(offset as u64));
+    output.set_permissions(metadata.permissions())?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+)))]
+fn copy_sparse(_source: &Path, _destination: &Path) -> io::Result<String> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Moves a file to another volume, where it can't simply be renamed: the
+/// file is copied and the original removed once the copy succeeded. If the
+/// move fails, the copy is removed and the original left as it was.
+pub fn move_across_devices(source: &Path, destination: &Path) -> io::Result<Vec<String>> {
+    let lost = copy_file(source, destination)?;
+    if let Err(e) = fs::remove_file(source) {
+        let _ = fs::remove_file(destination);
+        return Err(io::Error::new(
+            e.kind(),
+            format!("the original could not be removed: {}", e),
+        ));
+    }
+    Ok(lost)
+}
+
+/// Copies the extended attributes of `source` onto `destination` and returns
+/// the names of those that failed, e.g. because the destination's file
+/// system doesn't support them.
+#[cfg(unix)]
+fn copy_xattrs(source: &Path, destination: &Path) -> Vec<String> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Vec::new();
+    }
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(e) => return vec![format!("all extended attributes ({})", e)],
+    };
+    names
+        .filter(|name| match xattr::get(source, name) {
+            Ok(Some(value)) => xattr::set(destination, name, &value).is_err(),
+            // Removed in the meantime.
+            Ok(None) => false,
+            Err(_) => true,
+        })
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_source: &Path, _destination: &Path) -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh empty folder, unique to the test.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("move_files_gui_copy_{}", test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn copies_contents_without_leftovers() {
+        let dir = temp_dir("contents");
+        fs::write(dir.join("a.txt"), "hello").unwrap();
+        copy_file(&dir.join("a.txt"), &dir.join("b.txt")).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "hello");
+        assert_eq!(file_names(&dir), ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn copies_sparse_files_with_their_holes() {
+        let dir = temp_dir("sparse");
+        let source = dir.join("disk.img");
+        let mut file = File::create(&source).unwrap();
+        file.set_len(8 * 1024 * 1024).unwrap();
+        io::Seek::seek(&mut file, io::SeekFrom::Start(4 * 1024 * 1024)).unwrap();
+        io::Write::write_all(&mut file, b"data").unwrap();
+        drop(file);
+        copy_file(&source, &dir.join("copy.img")).unwrap();
+        assert!(fs::read(dir.join("copy.img")).unwrap() == fs::read(&source).unwrap());
+    }
+
+    #[test]
+    fn hashes_what_it_copies() {
+        let dir = temp_dir("hash");
+        let source = dir.join("a.bin");
+        let mut file = File::create(&source).unwrap();
+        file.set_len(3 * 1024 * 1024).unwrap();
+        io::Seek::seek(&mut file, io::SeekFrom::Start(1024 * 1024)).unwrap();
+        io::Write::write_all(&mut file, b"data").unwrap();
+        drop(file);
+        let expected = hashing::hash_file(&source).unwrap();
+        assert_eq!(
+            copy_contents(&source, &dir.join("b.bin")).unwrap(),
+            expected
+        );
+        if let Ok(hash) = copy_sparse(&source, &dir.join("c.bin")) {
+            assert_eq!(hash, expected);
+        }
+    }
+
+    #[test]
+    fn never_touches_an_existing_destination() {
+        let dir = temp_dir("existing");
+        fs::write(dir.join("a.txt"), "new").unwrap();
+        fs::write(dir.join("b.txt"), "theirs").unwrap();
+        let e = copy_file(&dir.join("a.txt"), &dir.join("b.txt")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "theirs");
+    }
+
+    #[test]
+    fn failed_copy_leaves_nothing_behind() {
+        let dir = temp_dir("failed");
+        assert!(copy_file(&dir.join("missing.txt"), &dir.join("b.txt")).is_err());
+        assert!(file_names(&dir).is_empty());
+    }
+
+    #[test]
+    fn move_across_devices_removes_the_original() {
+        let dir = temp_dir("move");
+        fs::write(dir.join("a.txt"), "moved").unwrap();
+        move_across_devices(&dir.join("a.txt"), &dir.join("b.txt")).unwrap();
+        assert_eq!(file_names(&dir), ["b.txt"]);
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "moved");
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::logging::LogEntry;
+use crate::mover::{Outcome, Plan, PlannedMove, Reporter, Status};
+
+/// Compares a run with the preview (dry run) the user looked at before it,
+/// so changes to the folders in between don't go unnoticed.
+pub struct DryRunCheck {
+    /// Destination shown in the preview for each source.
+    previewed: HashMap<PathBuf, PathBuf>,
+    /// Files moved that weren't in the preview.
+    appeared: Vec<PathBuf>,
+    /// Files in the preview that weren't moved, as they were gone by the time
+    /// the run was planned or executed.
+    disappeared: Vec<PathBuf>,
+    /// Files that ended up somewhere else than previewed, mostly because a
+    /// file with the same name showed up at the destination.
+    redirected: Vec<(PathBuf, PathBuf)>,
+}
+
+impl DryRunCheck {
+    pub fn new(preview: &Plan, plan: &Plan) -> Self {
+        let previewed: HashMap<PathBuf, PathBuf> = preview
+            .moves
+            .iter()
+            .map(|m| (m.source.clone(), m.destination.clone()))
+            .collect();
+        let planned: HashSet<&PathBuf> = plan.moves.iter().map(|m| &m.source).collect();
+        let appeared = plan
+            .moves
+            .iter()
+            .filter(|m| !previewed.contains_key(&m.source))
+            .map(|m| m.source.clone())
+            .collect();
+        let disappeared = preview
+            .moves
+            .iter()
+            .filter(|m| !planned.contains(&m.source))
+            .map(|m| m.source.clone())
+            .collect();
+        Self {
+            previewed,
+            appeared,
+            disappeared,
+            redirected: Vec::new(),
+        }
+    }
+
+    /// Checks where a file of the run actually went.
+    pub fn record(&mut self, planned: &PlannedMove, outcome: &Outcome) {
+        let Some(expected) = self.previewed.get(&planned.source) else {
+            return;
+        };
+        match outcome.status {
+            Status::Skipped => self.disappeared.push(planned.source.clone()),
+            Status::Moved | Status::Copied if outcome.destination != *expected => self
+                .redirected
+                .push((planned.source.clone(), outcome.destination.clone())),
+            _ => {}
+        }
+    }
+
+    /// Logs every divergence from the preview and a summary.
+    pub fn report(&self, reporter: &Reporter) {
+        for source in &self.appeared {
+            reporter.log(LogEntry::warning(format!(
+                "Not in the preview: {}",
+                source.display()
+            )));
+        }
+        for source in &self.disappeared {
+            reporter.log(LogEntry::warning(format!(
+                "In the preview but gone: {}",
+                source.display()
+            )));
+        }
+        for (source, destination) in &self.redirected {
+            reporter.log(LogEntry::warning(format!(
+                "Unexpected collision: {} went to {} instead of {}",
+                source.display(),
+                destination.display(),
+                self.previewed[source].display()
+            )));
+        }
+        if self.appeared.is_empty() && self.disappeared.is_empty() && self.redirected.is_empty() {
+            reporter.log(LogEntry::info("The run matched the preview."));
+        } else {
+            reporter.log(LogEntry::warning(format!(
+                "The run differed from the preview: {} files appeared, {} disappeared, \
+                 {} unexpected collisions.",
+                self.appeared.len(),
+                self.disappeared.len(),
+                self.redirected.len()
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(sources: &[&str]) -> Plan {
+        Plan {
+            input_path: "in".into(),
+            output_path: "out".into(),
+            moves: sources
+                .iter()
+                .map(|source| PlannedMove {
+                    source: PathBuf::from("in").join(source),
+                    destination: PathBuf::from("out").join(source),
+                    size: 0,
+                    collision: false,
+                    rule: None,
+                    owner: None,
+                })
+                .collect(),
+            duplicates: Vec::new(),
+            copy: false,
+            rules: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn finds_files_that_appeared_or_disappeared() {
+        let check = DryRunCheck::new(&plan(&["a", "b"]), &plan(&["b", "c"]));
+        assert_eq!(check.appeared, [PathBuf::from("in/c")]);
+        assert_eq!(check.disappeared, [PathBuf::from("in/a")]);
+    }
+
+    #[test]
+    fn records_files_that_went_elsewhere() {
+        let run = plan(&["a", "b", "c"]);
+        let mut check = DryRunCheck::new(&run, &run);
+        let outcome = |status, destination: &str| Outcome {
+            status,
+            destination: PathBuf::from(destination),
+            error: None,
+        };
+        check.record(&run.moves[0], &outcome(Status::Moved, "out/a"));
+        check.record(&run.moves[1], &outcome(Status::Moved, "out/b_1"));
+        check.record(&run.moves[2], &outcome(Status::Skipped, "out/c"));
+        assert!(check.appeared.is_empty());
+        assert_eq!(check.disappeared, [PathBuf::from("in/c")]);
+        assert_eq!(
+            check.redirected,
+            [(PathBuf::from("in/b"), PathBuf::from("out/b_1"))]
+        );
+    }
+}
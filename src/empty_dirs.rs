@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::logging::LogEntry;
+use crate::mover::{Reporter, WorkerMessage};
+use crate::protected;
+
+/// Files that don't keep a folder from counting as empty, when enabled.
+pub const DEFAULT_JUNK_FILES: &str = "Thumbs.db, .DS_Store, desktop.ini";
+
+/// Parses a comma-separated list of file names. Names are compared
+/// case-insensitively, like on Windows.
+pub fn parse_junk_files(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// True if `dir` holds nothing but junk files and folders already found empty.
+fn is_empty(dir: &Path, junk_files: &[String], empty: &HashSet<PathBuf>) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.into_iter().all(|entry| {
+        let Ok(entry) = entry else {
+            return false;
+        };
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => empty.contains(&entry.path()),
+            Ok(file_type) if file_type.is_file() => {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                junk_files.contains(&name)
+            }
+            // Symlinks and anything else keep the folder.
+            _ => false,
+        }
+    })
+}
+
+/// This function runs in a background thread. It finds every folder under
+/// `root` (not `root` itself) that contains no files, apart from `junk_files`,
+/// and sends the list back via the provided reporter. Folders containing only
+/// empty folders count as empty too.
+pub fn scan_thread(
+    root: PathBuf,
+    junk_files: Vec<String>,
+    protected_paths: String,
+    reporter: Reporter,
+) {
+    if !root.is_dir() {
+        reporter.log(LogEntry::error(format!(
+            "{} is not a valid directory.",
+            root.display()
+        )));
+        return;
+    }
+    // Empty system folders and mount points are often needed.
+    if let Some(entry) = protected::blocking_entry(&root, &protected_paths, true) {
+        reporter.log(LogEntry::error(format!(
+            "Refusing to clean up {}: it is protected by \"{}\". \
+                 Edit the protected paths list to allow it.",
+            root.display(),
+            entry
+        )));
+        return;
+    }
+    let root = std::path::absolute(&root).unwrap_or(root);
+    let mut empty = HashSet::new();
+    let mut found = Vec::new();
+    // Children are visited before their parent, so nested empty folders are
+    // known by the time the parent is checked.
+    for entry in WalkDir::new(&root)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+    {
+        if is_empty(entry.path(), &junk_files, &empty) {
+            empty.insert(entry.path().to_path_buf());
+            found.push(entry.into_path());
+        }
+    }
+    // Outermost folders first reads better in the list.
+    found.reverse();
+    reporter.log(LogEntry::info(format!(
+        "Found {} empty folders in {}.",
+        found.len(),
+        root.display()
+    )));
+    reporter.send(WorkerMessage::EmptyDirs(found));
+}
+
+/// Of the given folders, only those not inside another one in the list.
+/// Trashing those takes the nested ones along.
+fn outermost(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let all: HashSet<&Path> = dirs.iter().map(PathBuf::as_path).collect();
+    dirs.iter()
+        .filter(|dir| !dir.ancestors().skip(1).any(|parent| all.contains(parent)))
+        .cloned()
+        .collect()
+}
+
+/// This function runs in a background thread. It moves the empty folders
+/// found by a scan to the recycle bin / trash and sends back the folders
+/// trashed, for undo. Each folder is checked again first, and folders that
+/// got files since the scan are left alone along with their parents.
+pub fn trash_thread(found: Vec<PathBuf>, junk_files: Vec<String>, reporter: Reporter) {
+    let mut empty = HashSet::new();
+    // The scan lists outermost folders first, so nested ones come first here.
+    for dir in found.iter().rev() {
+        if is_empty(dir, &junk_files, &empty) {
+            empty.insert(dir.clone());
+        } else {
+            reporter.log(LogEntry::warning(format!(
+                "Skipped {}: it is no longer empty.",
+                dir.display()
+            )));
+        }
+    }
+    let still_empty: Vec<PathBuf> = found.into_iter().filter(|d| empty.contains(d)).collect();
+    let dirs = outermost(&still_empty);
+    match trash::delete_all(&dirs) {
+        Ok(()) => {
+            reporter.log(LogEntry::info(format!(
+                "Moved {} empty folders to the recycle bin.",
+                still_empty.len()
+            )));
+            reporter.send(WorkerMessage::Trashed(dirs));
+        }
+        Err(e) => reporter.log(LogEntry::error(format!(
+            "Could not move folders to the recycle bin: {}",
+            e
+        ))),
+    }
+}
+
+/// Whether trashed folders can be put back. The trash crate can only list
+/// and restore trash items on Windows and freedesktop systems.
+pub const CAN_RESTORE: bool = cfg!(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+));
+
+/// This function runs in a background thread. It puts the folders of the
+/// last cleanup back, or sends them back to be tried again if that failed.
+pub fn restore_thread(dirs: Vec<PathBuf>, reporter: Reporter) {
+    match restore(&dirs) {
+        Ok(count) => reporter.log(LogEntry::info(format!(
+            "Restored {} folders from the recycle bin.",
+            count
+        ))),
+        Err(e) => {
+            reporter.log(LogEntry::error(format!(
+                "Could not restore folders from the recycle bin: {}",
+                e
+            )));
+            reporter.send(WorkerMessage::Trashed(dirs));
+        }
+    }
+}
+
+/// Restores the most recently trashed item for each of the given paths.
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore(dirs: &[PathBuf]) -> Result<usize, trash::Error> {
+    use std::collections::HashMap;
+
+    let wanted: HashSet<&PathBuf> = dirs.iter().collect();
+    let mut newest: HashMap<PathBuf, trash::TrashItem> = HashMap::new();
+    for item in trash::os_limited::list()? {
+        let path = item.original_path();
+        if !wanted.contains(&path) {
+            continue;
+        }
+        match newest.get(&path) {
+            Some(existing) if existing.time_deleted >= item.time_deleted => {}
+            _ => {
+                newest.insert(path, item);
+            }
+        }
+    }
+    let count = newest.len();
+    trash::os_limited::restore_all(newest.into_values())?;
+    Ok(count)
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore(_dirs: &[PathBuf]) -> Result<usize, trash::Error> {
+    Err(trash::Error::Unknown {
+        description: "restoring from the trash isn't supported on this platform".to_string(),
+    })
+}
@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lists every file under `dir` by asking a running Everything instance
+/// (voidtools) through its command-line client `es.exe`, which must be on the
+/// PATH. This answers instantly from Everything's index instead of walking
+/// millions of entries, so callers should fall back to walking on error.
+pub fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    // Results are exported to a file rather than read from stdout, which
+    // would be in the console's code page instead of UTF-8.
+    let export_path = env::temp_dir().join(format!("move_files_es_{}.txt", std::process::id()));
+    let mut command = Command::new("es.exe");
+    command
+        .arg("-path")
+        .arg(dir)
+        // Files only, no folders.
+        .arg("/a-d")
+        .arg("-export-txt")
+        .arg(&export_path);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Don't flash a console window from the GUI.
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let status = command.status()?;
+    let exported = fs::read_to_string(&export_path);
+    let _ = fs::remove_file(&export_path);
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "es.exe failed ({}), is Everything running?",
+            status
+        )));
+    }
+    Ok(exported?
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+/// Alias groups used until the user edits them. Extensions in the same group
+/// are treated as one type, so filtering for "jpg" also picks up ".jpeg" files.
+pub const DEFAULT_ALIASES: &str = "jpg/jpeg, htm/html, tif/tiff, mpg/mpeg";
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Parses a comma-separated list of alias groups (e.g. "jpg/jpeg, tif/tiff")
+/// into groups of normalized extensions. Groups with fewer than two
+/// extensions don't alias anything and are dropped.
+pub fn parse_aliases(aliases: &str) -> Vec<Vec<String>> {
+    aliases
+        .split(',')
+        .map(|group| {
+            group
+                .split('/')
+                .map(normalize_extension)
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Parses a comma-separated extensions string (e.g. "pdf, .JPG, png") into a
+/// vector of normalized (lowercase, without dot) extensions, including every
+/// alias of the listed extensions.
+/// Blank entries are dropped, so an empty string yields an empty vector.
+pub fn parse_extensions(extensions: &str, aliases: &[Vec<String>]) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for ext in extensions.split(',').map(normalize_extension) {
+        if ext.is_empty() {
+            continue;
+        }
+        let members = match aliases.iter().find(|group| group.contains(&ext)) {
+            Some(group) => group.clone(),
+            None => vec![ext],
+        };
+        for ext in members {
+            if !parsed.contains(&ext) {
+                parsed.push(ext);
+            }
+        }
+    }
+    parsed
+}
+
+/// Returns true if the file's extension (in lowercase) is in `filter_exts`.
+/// An empty filter matches every file.
+pub fn matches_extension(filter_exts: &[String], file_path: &Path) -> bool {
+    if filter_exts.is_empty() {
+        return true;
+    }
+    match file_path.extension().and_then(|s| s.to_str()) {
+        Some(ext) => filter_exts.contains(&ext.to_lowercase()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_are_normalized_and_single_groups_dropped() {
+        assert_eq!(
+            parse_aliases(" .JPG/jpeg , tif, htm/ /html"),
+            vec![vec!["jpg", "jpeg"], vec!["htm", "html"]]
+        );
+        assert!(parse_aliases("").is_empty());
+    }
+
+    #[test]
+    fn extensions_include_their_aliases_once() {
+        let aliases = parse_aliases(DEFAULT_ALIASES);
+        assert_eq!(
+            parse_extensions("pdf, .JPG, , jpeg", &aliases),
+            vec!["pdf", "jpg", "jpeg"]
+        );
+        assert!(parse_extensions(" , ", &aliases).is_empty());
+    }
+
+    #[test]
+    fn extensions_match_case_insensitively() {
+        let extensions = parse_extensions("jpg", &[]);
+        assert!(matches_extension(&extensions, Path::new("photo.JPG")));
+        assert!(!matches_extension(&extensions, Path::new("photo.png")));
+        assert!(!matches_extension(&extensions, Path::new("jpg")));
+        assert!(matches_extension(&[], Path::new("anything")));
+    }
+}
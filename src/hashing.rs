@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const CACHE_FILE: &str = "hash_cache.json";
+
+/// Files at least this big are hashed on all cores, chunk by chunk.
+const PARALLEL_THRESHOLD: u64 = 16 * 1024 * 1024;
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Hashes the whole file with BLAKE3 and returns the hex digest. Files are
+/// read rather than memory-mapped, as other programs may be writing or
+/// truncating them meanwhile.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    if file.metadata()?.len() >= PARALLEL_THRESHOLD {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+        loop {
+            chunk.clear();
+            (&mut file).take(CHUNK_SIZE).read_to_end(&mut chunk)?;
+            if chunk.is_empty() {
+                break;
+            }
+            hasher.update_rayon(&chunk);
+        }
+    } else {
+        hasher.update_reader(file)?;
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Size and modification time, which decide whether a cached hash is still valid.
+fn stamp(path: &Path) -> io::Result<(u64, u128)> {
+    let metadata = fs::metadata(path)?;
+    let modified_nanos = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    Ok((metadata.len(), modified_nanos))
+}
+
+/// A previously computed hash, valid as long as the file's size and
+/// modification time haven't changed.
+#[derive(Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    modified_nanos: u128,
+    hash: String,
+}
+
+/// Hashes remembered across runs, so repeated comparisons against a large
+/// archive don't re-read unchanged files.
+#[derive(Default)]
+pub struct HashCache {
+    entries: HashMap<PathBuf, CachedHash>,
+    dirty: bool,
+}
+
+impl HashCache {
+    /// Loads the cache from the app's data folder, starting empty if there is
+    /// none yet or it can't be read.
+    pub fn load() -> Self {
+        let entries = storage::data_dir()
+            .and_then(|dir| fs::read(dir.join(CACHE_FILE)))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+        Self {
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Writes the cache back to disk if anything changed since it was loaded.
+    /// Entries of files that were moved, deleted or changed since are dropped
+    /// first; they would never be used again.
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.entries.retain(|path, cached| {
+            stamp(path).is_ok_and(|(size, modified_nanos)| {
+                size == cached.size && modified_nanos == cached.modified_nanos
+            })
+        });
+        let json = serde_json::to_vec(&self.entries)?;
+        storage::write_atomic(&storage::data_dir()?.join(CACHE_FILE), &json)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn cached(&self, path: &Path, size: u64, modified_nanos: u128) -> Option<&str> {
+        self.entries
+            .get(path)
+            .filter(|cached| cached.size == size && cached.modified_nanos == modified_nanos)
+            .map(|cached| cached.hash.as_str())
+    }
+
+    fn insert(&mut self, path: PathBuf, size: u64, modified_nanos: u128, hash: String) {
+        self.entries.insert(
+            path,
+            CachedHash {
+                size,
+                modified_nanos,
+                hash,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Returns the file's hash, reading the file only if it isn't cached or
+    /// has changed since it was last hashed.
+    pub fn hash(&mut self, path: &Path) -> io::Result<String> {
+        let (size, modified_nanos) = stamp(path)?;
+        if let Some(hash) = self.cached(path, size, modified_nanos) {
+            return Ok(hash.to_string());
+        }
+        let hash = hash_file(path)?;
+        self.insert(path.to_path_buf(), size, modified_nanos, hash.clone());
+        Ok(hash)
+    }
+
+    /// Hashes every uncached file in `paths` in parallel, so that later
+    /// `hash` calls are answered from the cache. Files are spread over all
+    /// cores, so while some threads wait on the disk others are hashing.
+    /// Unreadable files are skipped; `hash` reports their error later.
+    pub fn hash_all(&mut self, paths: &[PathBuf]) {
+        let mut seen = HashSet::new();
+        let missing: Vec<(&PathBuf, u64, u128)> = paths
+            .iter()
+            .filter(|path| seen.insert(*path))
+            .filter_map(|path| {
+                let (size, modified_nanos) = stamp(path).ok()?;
+                self.cached(path, size, modified_nanos)
+                    .is_none()
+                    .then_some((path, size, modified_nanos))
+            })
+            .collect();
+        let computed: Vec<_> = missing
+            .into_par_iter()
+            .filter_map(|(path, size, modified_nanos)| {
+                let hash = hash_file(path).ok()?;
+                Some((path.clone(), size, modified_nanos, hash))
+            })
+            .collect();
+        for (path, size, modified_nanos, hash) in computed {
+            self.insert(path, size, modified_nanos, hash);
+        }
+    }
+}
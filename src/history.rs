@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::time::Duration;
+
+use chrono::Local;
+use rusqlite::{params, Connection};
+
+use crate::mover::{Outcome, Plan, PlannedMove};
+use crate::storage;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        finished_at TEXT,
+        input_path TEXT NOT NULL,
+        output_path TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS operations (
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        time TEXT NOT NULL,
+        source TEXT NOT NULL,
+        destination TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        error TEXT,
+        duration_ms INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS operations_run_id ON operations(run_id);
+";
+
+/// Totals of a past run, for the history panel.
+pub struct RunSummary {
+    pub started_at: String,
+    pub input_path: String,
+    pub output_path: String,
+    pub moved: u64,
+    pub failed: u64,
+    pub bytes_moved: u64,
+}
+
+/// Local database of every run and every file operation, kept across restarts.
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let conn = storage::open_database()?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Records the start of a run and returns its id.
+    pub fn start_run(&self, plan: &Plan) -> rusqlite::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO runs (started_at, input_path, output_path) VALUES (?1, ?2, ?3)",
+            params![Local::now().to_rfc3339(), plan.input_path, plan.output_path],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn finish_run(&self, run_id: i64) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+            params![Local::now().to_rfc3339(), run_id],
+        )?;
+        Ok(())
+    }
+
+    /// Starts a transaction. Operations are committed in batches by
+    /// `checkpoint`, as committing every file would slow large runs down.
+    pub fn begin(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("BEGIN")
+    }
+
+    pub fn checkpoint(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("COMMIT; BEGIN")
+    }
+
+    pub fn commit(&self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("COMMIT")
+    }
+
+    pub fn record(
+        &self,
+        run_id: i64,
+        planned: &PlannedMove,
+        outcome: &Outcome,
+        duration: Duration,
+    ) -> rusqlite::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO operations
+                    (run_id, time, source, destination, size, status, error, duration_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?
+            .execute(params![
+                run_id,
+                Local::now().to_rfc3339(),
+                planned.source.to_string_lossy(),
+                outcome.destination.to_string_lossy(),
+                planned.size as i64,
+                outcome.status.as_str(),
+                outcome.error,
+                duration.as_millis() as i64,
+            ])?;
+        Ok(())
+    }
+
+    /// The most recent runs, newest first.
+    pub fn recent_runs(&self, limit: usize) -> rusqlite::Result<Vec<RunSummary>> {
+        let mut statement = self.conn.prepare(
+            "SELECT r.started_at, r.input_path, r.output_path,
+                    COUNT(CASE WHEN o.status IN ('moved', 'copied') THEN 1 END),
+                    COUNT(CASE WHEN o.status = 'failed' THEN 1 END),
+                    COALESCE(SUM(CASE WHEN o.status IN ('moved', 'copied') THEN o.size END), 0)
+             FROM runs r LEFT JOIN operations o ON o.run_id = r.id
+             GROUP BY r.id
+             ORDER BY r.id DESC
+             LIMIT ?1",
+        )?;
+        let rows = statement.query_map([limit as i64], |row| {
+            Ok(RunSummary {
+                started_at: row.get(0)?,
+                input_path: row.get(1)?,
+                output_path: row.get(2)?,
+                moved: row.get::<_, i64>(3)? as u64,
+                failed: row.get::<_, i64>(4)? as u64,
+                bytes_moved: row.get::<_, i64>(5)? as u64,
+            })
+        })?;
+        rows.collect()
+    }
+}
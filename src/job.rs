@@ -0,0 +1,73 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::mover::Plan;
+use crate::storage;
+
+// The plan is written once when a run starts; only the small progress file
+// is rewritten while it runs. Both are removed once the run completes, so
+// finding them on launch means the last run was interrupted.
+const PLAN_FILE: &str = "job.json";
+const PROGRESS_FILE: &str = "job.progress";
+
+fn job_path(name: &str) -> io::Result<PathBuf> {
+    Ok(storage::data_dir()?.join(name))
+}
+
+/// Records the plan of a run that is about to start.
+pub fn save_plan(plan: &Plan) -> io::Result<()> {
+    let json = serde_json::to_vec(plan)?;
+    storage::write_atomic(&job_path(PLAN_FILE)?, &json)?;
+    save_progress(0)
+}
+
+/// Records how many entries of the plan have been processed.
+pub fn save_progress(completed: usize) -> io::Result<()> {
+    storage::write_atomic(&job_path(PROGRESS_FILE)?, completed.to_string().as_bytes())
+}
+
+/// Forgets the current job, called when a run completes or is discarded.
+pub fn clear() {
+    for name in [PLAN_FILE, PROGRESS_FILE] {
+        if let Ok(path) = job_path(name) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Returns the interrupted job left behind by a previous run, if any, along
+/// with the number of entries it had already processed.
+pub fn load_interrupted() -> Option<(Plan, usize)> {
+    let json = fs::read(job_path(PLAN_FILE).ok()?).ok()?;
+    let Ok(plan) = serde_json::from_slice::<Plan>(&json) else {
+        // Unreadable leftovers can't be resumed; don't ask about them again.
+        clear();
+        return None;
+    };
+    let completed = fs::read_to_string(job_path(PROGRESS_FILE).ok()?)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+    Some((plan, completed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_plans_saved_by_older_versions() {
+        let json = r#"{
+            "input_path": "in",
+            "output_path": "out",
+            "moves": [
+                {"source": "in/a.txt", "destination": "out/a.txt", "size": 5, "collision": false}
+            ]
+        }"#;
+        let plan: Plan = serde_json::from_str(json).unwrap();
+        assert_eq!(plan.moves.len(), 1);
+        assert_eq!(plan.moves[0].rule, None);
+        assert!(!plan.copy && plan.duplicates.is_empty() && plan.rules.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One row of the log. Per-file operations carry how long they took, so slow
+/// files (network hiccups, antivirus scans) stand out after a long run.
+#[derive(Clone)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub message: String,
+    pub duration: Option<Duration>,
+}
+
+impl LogEntry {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            time: Local::now(),
+            level,
+            message: message.into(),
+            duration: None,
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(Level::Info, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Level::Warning, message)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Level::Error, message)
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.time.format("%H:%M:%S%.3f"), self.message)?;
+        match self.duration {
+            Some(duration) if duration.as_secs() >= 1 => {
+                write!(f, " ({:.2} s)", duration.as_secs_f64())
+            }
+            Some(duration) => write!(f, " ({} ms)", duration.as_millis()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How many entries the log keeps. Older entries are dropped once a run
+/// produces more, so moving hundreds of thousands of files can't exhaust memory.
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// Capped ring buffer of log entries.
+#[derive(Default)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    /// Number of entries dropped since the last clear.
+    dropped: usize,
+}
+
+impl LogBuffer {
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn get(&self, index: usize) -> Option<&LogEntry> {
+        self.entries.get(index)
+    }
+}
@@ -1,212 +1,1365 @@
 #![windows_subsystem = "windows"]
 
-use std::error::Error;
-use std::fs;
-use std::path::PathBuf;
+mod cli;
+mod compare;
+mod copy;
+mod dry_run;
+mod empty_dirs;
+mod everything;
+mod filters;
+mod hashing;
+mod history;
+mod job;
+mod logging;
+mod matches;
+mod mover;
+mod network;
+mod owner;
+mod profiles;
+mod protected;
+mod rename;
+mod rule_stats;
+mod rules;
+mod storage;
+mod tags;
+mod usage;
+
 use std::sync::mpsc;
 use std::thread;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use eframe::egui;
 use eframe::egui::IconData;
 use rfd::FileDialog;
-use walkdir::WalkDir;
+
+use compare::Comparison;
+use dry_run::DryRunCheck;
+use history::{History, RunSummary};
+use logging::{Level, LogBuffer, LogEntry};
+use matches::MatchCounter;
+use mover::{InputType, MoveSettings, Plan, PlannedMove, Reporter, WorkerMessage};
+use network::Credentials;
+use owner::OwnerNames;
+use rename::Renamer;
+use rules::Rule;
+use tags::TagWriter;
+use usage::Usage;
+
+/// Scan results shown without moving anything, where files can be tagged.
+struct Preview {
+    plan: Plan,
+    /// What the scan was run with; a later run is only checked against the
+    /// preview if it used the very same settings.
+    settings: MoveSettings,
+    /// Editable comma-separated tags, one per planned move.
+    tags: Vec<String>,
+    /// Owner of each file; only filled in on Unix.
+    owners: Vec<String>,
+}
+
+impl Preview {
+    fn new(mut plan: Plan, settings: MoveSettings) -> Self {
+        let tags = std::mem::take(&mut plan.tags);
+        let owners = if cfg!(unix) {
+            let names = OwnerNames::default();
+            plan.moves
+                .iter()
+                .map(|planned| planned.owner.map(|uid| names.name(uid)).unwrap_or_default())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            plan,
+            settings,
+            tags,
+            owners,
+        }
+    }
+}
 
 #[derive(PartialEq, Eq, Clone, Copy)]
-enum InputType {
-    File,
-    Directory,
+enum Tab {
+    Move,
+    Rename,
+    Compare,
+    EmptyFolders,
+}
+
+/// State of the "Rename" tab.
+#[derive(Default)]
+struct RenameTab {
+    folder: String,
+    recursive: bool,
+    /// Only files with these extensions are renamed; empty means all.
+    extensions: String,
+    /// Renames waiting for confirmation, shown as a before/after preview.
+    planned: Vec<PlannedMove>,
+    /// Renames carried out by the last run, for undo.
+    done: Vec<PlannedMove>,
+}
+
+/// State of the "Compare Folders" tab.
+#[derive(Default)]
+struct CompareTab {
+    left: String,
+    right: String,
+    comparison: Option<Comparison>,
+}
+
+/// State of the "Empty Folders" tab.
+struct EmptyFoldersTab {
+    folder: String,
+    /// Also count folders holding nothing but `junk_files` as empty.
+    ignore_junk: bool,
+    junk_files: String,
+    found: Vec<PathBuf>,
+    /// Folders moved to the recycle bin by the last cleanup, for undo.
+    trashed: Vec<PathBuf>,
+    /// True while asking the user to confirm moving `found` to the recycle bin.
+    confirm_trash: bool,
+}
+
+impl Default for EmptyFoldersTab {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            ignore_junk: true,
+            junk_files: empty_dirs::DEFAULT_JUNK_FILES.to_string(),
+            found: Vec::new(),
+            trashed: Vec::new(),
+            confirm_trash: false,
+        }
+    }
+}
+
+impl EmptyFoldersTab {
+    /// The junk file names, if they are to be ignored.
+    fn junk_files(&self) -> Vec<String> {
+        if self.ignore_junk {
+            empty_dirs::parse_junk_files(&self.junk_files)
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 struct MyApp {
+    tab: Tab,
     input_path: String,
     /// Comma-separated list of file extensions (e.g., "pdf, jpg, png")
     extensions: String,
+    /// Comma-separated groups of equivalent extensions (e.g., "jpg/jpeg, tif/tiff")
+    aliases: String,
     output_path: String,
     input_type: InputType,
-    log: String,
-    /// Receiver for log messages coming from the background thread.
-    log_rx: Option<mpsc::Receiver<String>>,
-    /// Flag indicating if the move operation is running.
+    log: LogBuffer,
+    /// Receiver for messages coming from the background thread.
+    log_rx: Option<mpsc::Receiver<WorkerMessage>>,
+    /// Flag indicating if a scan or move operation is running.
     is_moving: bool,
+    /// Files processed and total files of the running move, if any.
+    progress: Option<(usize, usize)>,
+    /// Scan results waiting for the user to confirm or cancel the move.
+    pending_plan: Option<Plan>,
+    /// True if the running scan was started by "Preview" rather than "Move Files".
+    scan_for_preview: bool,
+    /// Settings of the last scan, so a run can be matched to its preview.
+    scan_settings: MoveSettings,
+    preview: Option<Preview>,
+    /// Tag applied by "Tag Files...".
+    tag_to_apply: String,
+    tag_writer: TagWriter,
+    /// A job left unfinished by a previous session, with the number of
+    /// entries it had processed, waiting for the user to resume or discard it.
+    interrupted_job: Option<(Plan, usize)>,
+    /// Most recent runs from the history database, refreshed after each run.
+    history_runs: Vec<RunSummary>,
+    /// When enabled, files are sorted into sub-folders by `rules` instead of
+    /// being filtered by `extensions`.
+    use_rules: bool,
+    rules: Vec<Rule>,
+    /// Locations refused as input or output, one per line.
+    protected_paths: String,
+    /// Leave files alone if an identical one already exists at the destination.
+    skip_duplicates: bool,
+    /// Copy files instead of moving them.
+    copy: bool,
+    /// Use the Everything index instead of walking the input (Windows only).
+    use_index: bool,
+    /// Only move files owned by this user (Unix only).
+    owner: String,
+    /// Log into network shares before moving (Windows only). The password
+    /// is only kept in memory.
+    connect_shares: bool,
+    share_credentials: Credentials,
+    match_counter: MatchCounter,
+    /// Disk usage of the input folder, shown until closed.
+    usage: Option<Usage>,
+    usage_by_category: bool,
+    /// New file names, shared by the mover and the Rename tab.
+    rename_template: String,
+    rename_replacements: String,
+    renamer: RenameTab,
+    compare: CompareTab,
+    empty_folders: EmptyFoldersTab,
+    /// Name typed or picked in the profile controls.
+    profile_name: String,
+    /// Saved profiles, refreshed when one is saved or deleted.
+    profile_names: Vec<String>,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
         Self {
+            tab: Tab::Move,
             input_path: String::new(),
             extensions: String::new(),
+            aliases: filters::DEFAULT_ALIASES.to_string(),
             output_path: String::new(),
             input_type: InputType::Directory, // usually this will probably be a folder
-            log: String::new(),
+            log: LogBuffer::default(),
             log_rx: None,
             is_moving: false,
+            progress: None,
+            pending_plan: None,
+            scan_for_preview: false,
+            scan_settings: MoveSettings::default(),
+            preview: None,
+            tag_to_apply: String::new(),
+            tag_writer: TagWriter::default(),
+            interrupted_job: None,
+            history_runs: Vec::new(),
+            match_counter: MatchCounter::default(),
+            usage: None,
+            usage_by_category: false,
+            rename_template: String::new(),
+            rename_replacements: String::new(),
+            renamer: RenameTab::default(),
+            compare: CompareTab::default(),
+            empty_folders: EmptyFoldersTab::default(),
+            use_rules: false,
+            rules: Vec::new(),
+            protected_paths: protected::default_list(),
+            skip_duplicates: false,
+            copy: false,
+            use_index: false,
+            owner: String::new(),
+            connect_shares: false,
+            share_credentials: Credentials::default(),
+            profile_name: String::new(),
+            profile_names: Vec::new(),
         }
     }
 }
 
-/// This function runs in a background thread. It recursively scans the input path
-/// and moves all files with the specified extensions to the output folder,
-/// sending progress messages back via the provided channel.
-/// If the extensions string is empty, then every file is moved.
-fn move_files_thread(
-    input_path: String,
-    output_path: String,
-    extensions: String,
-    input_type: InputType,
-    sender: mpsc::Sender<String>,
-) -> Result<(), Box<dyn Error>> {
-    let output_dir = PathBuf::from(&output_path);
-    fs::create_dir_all(&output_dir)?;
-    
-    // Parse the extensions string into a vector of normalized (lowercase, without dot) extensions.
-    // If the user leaves this field blank, filter_exts will be empty.
-    let filter_exts: Vec<String> = extensions
-        .split(',')
-        .map(|s| s.trim().trim_start_matches('.').to_lowercase())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    if input_type == InputType::Directory {
-        let input_dir = PathBuf::from(&input_path);
-        if !input_dir.is_dir() {
-            let _ = sender.send(format!("{} is not a valid directory.\n", input_dir.display()));
-            return Err(format!("{} is not a valid directory.", input_dir.display()).into());
-        }
-        // Walk the directory recursively.
-        for entry in WalkDir::new(&input_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let file_path = entry.path();
-            // Determine if the file should be moved:
-            // - If filter_exts is empty, move every file.
-            // - Otherwise, only move files whose extension (in lowercase) is in filter_exts.
-            let should_move = if filter_exts.is_empty() {
-                true
-            } else if let Some(ext) = file_path.extension().and_then(|s| s.to_str()) {
-                filter_exts.contains(&ext.to_lowercase())
-            } else {
-                false
-            };
-
-            if should_move {
-                // Determine the output file path using the original file name.
-                if let Some(file_name) = file_path.file_name() {
-                    let mut dest_path = output_dir.join(file_name);
-                    // If a file with the same name exists in the output, add a counter to avoid collision.
-                    let mut counter = 1;
-                    while dest_path.exists() {
-                        let stem = file_path
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("file");
-                        let new_name = if let Some(extension) = file_path.extension().and_then(|s| s.to_str()) {
-                            format!("{}_{}.{}", stem, counter, extension)
-                        } else {
-                            format!("{}_{}", stem, counter)
-                        };
-                        dest_path = output_dir.join(new_name);
-                        counter += 1;
+impl MyApp {
+    /// Opens with the settings of the named profile, if given.
+    fn new(profile: Option<String>) -> Self {
+        let mut app = Self {
+            interrupted_job: job::load_interrupted(),
+            profile_names: profiles::names(),
+            ..Self::default()
+        };
+        app.refresh_history();
+        if let Some(name) = profile {
+            app.load_profile(&name);
+        }
+        app
+    }
+
+    fn load_profile(&mut self, name: &str) {
+        match profiles::load(name) {
+            Ok(settings) => {
+                self.apply_settings(settings);
+                self.profile_name = name.trim().to_string();
+                self.log.push(LogEntry::info(format!(
+                    "Loaded profile \"{}\".",
+                    name.trim()
+                )));
+            }
+            Err(e) => self.log.push(LogEntry::error(format!(
+                "Could not load the profile: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Puts saved settings into the Move tab, the reverse of `settings()`.
+    fn apply_settings(&mut self, settings: MoveSettings) {
+        let MoveSettings {
+            input_path,
+            output_path,
+            extensions,
+            aliases,
+            input_type,
+            rules,
+            // The app-wide list stays, so a profile can't turn it off.
+            protected_paths: _,
+            skip_duplicates,
+            use_index,
+            copy,
+            share_login,
+            owner,
+            rename_template,
+            rename_replacements,
+        } = settings;
+        self.input_path = input_path;
+        self.output_path = output_path;
+        self.extensions = extensions;
+        self.aliases = aliases;
+        self.input_type = input_type;
+        // The current rules stay in the table if the profile doesn't use any.
+        self.use_rules = rules.is_some();
+        if let Some(rules) = rules {
+            self.rules = rules;
+        }
+        self.skip_duplicates = skip_duplicates;
+        self.use_index = use_index;
+        self.copy = copy;
+        self.connect_shares = share_login.is_some();
+        // Profiles have no passwords; one typed for the same user is kept.
+        if let Some(credentials) = share_login {
+            if credentials.username != self.share_credentials.username {
+                self.share_credentials = credentials;
+            }
+        }
+        self.owner = owner;
+        self.rename_template = rename_template;
+        self.rename_replacements = rename_replacements;
+    }
+
+    /// Saving, loading and deleting named profiles of the Move tab.
+    fn profiles_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            ui.text_edit_singleline(&mut self.profile_name);
+            let mut picked = None;
+            egui::ComboBox::from_id_salt("profiles")
+                .selected_text("Load...")
+                .show_ui(ui, |ui| {
+                    for name in &self.profile_names {
+                        if ui.selectable_label(false, name).clicked() {
+                            picked = Some(name.clone());
+                        }
                     }
-                    // Attempt to move (rename) the file.
-                    match fs::rename(file_path, &dest_path) {
-                        Ok(_) => {
-                            let _ = sender.send(format!(
-                                "Moved: {} -> {}\n",
-                                file_path.display(),
-                                dest_path.display()
-                            ));
+                });
+            if let Some(name) = picked {
+                self.load_profile(&name);
+            }
+            let name = self.profile_name.trim().to_string();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Share passwords are not saved in profiles.")
+                .clicked()
+            {
+                match profiles::save(&name, &self.settings()) {
+                    Ok(()) => self
+                        .log
+                        .push(LogEntry::info(format!("Saved profile \"{}\".", name))),
+                    Err(e) => self.log.push(LogEntry::error(format!(
+                        "Could not save the profile: {}",
+                        e
+                    ))),
+                }
+                self.profile_names = profiles::names();
+            }
+            if ui
+                .add_enabled(
+                    self.profile_names.contains(&name),
+                    egui::Button::new("Delete"),
+                )
+                .clicked()
+            {
+                if let Err(e) = profiles::delete(&name) {
+                    self.log.push(LogEntry::error(format!(
+                        "Could not delete the profile: {}",
+                        e
+                    )));
+                }
+                self.profile_names = profiles::names();
+            }
+            if self.connect_shares && !self.share_credentials.username.trim().is_empty() {
+                ui.label("(the share password is not saved)");
+            }
+        });
+    }
+
+    fn refresh_history(&mut self) {
+        const HISTORY_ROWS: usize = 20;
+        match History::open().and_then(|history| Ok(history.recent_runs(HISTORY_ROWS)?)) {
+            Ok(runs) => self.history_runs = runs,
+            Err(e) => self.log.push(LogEntry::warning(format!(
+                "Could not read the history database: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Tags the files picked in a file dialog with `tag_to_apply`.
+    fn tag_files(&mut self, ctx: &egui::Context) {
+        let tag = self.tag_to_apply.trim().to_lowercase();
+        if tag.is_empty() {
+            return;
+        }
+        let Some(files) = FileDialog::new().pick_files() else {
+            return;
+        };
+        self.tag_writer.add_tag(ctx, files, tag);
+    }
+
+    /// Table of the files a scan would move, with their tags editable.
+    /// Only the visible rows are laid out, as a preview can be huge.
+    fn preview_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(preview) = &mut self.preview else {
+            return;
+        };
+        let mut close = false;
+        let mut edited = None;
+        ui.horizontal(|ui| {
+            ui.strong(format!(
+                "Preview: {} files ({}) would be {}, {} collisions.",
+                preview.plan.moves.len(),
+                mover::format_size(preview.plan.total_bytes()),
+                if preview.plan.copy { "copied" } else { "moved" },
+                preview.plan.collisions()
+            ));
+            close = ui.button("Close").clicked();
+        });
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::both()
+            .id_salt("preview")
+            .max_height(250.0)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, preview.plan.moves.len(), |ui, rows| {
+                for row in rows {
+                    let planned = &preview.plan.moves[row];
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            [260.0, row_height],
+                            egui::Label::new(planned.source.display().to_string()).truncate(),
+                        );
+                        ui.label("->");
+                        ui.add_sized(
+                            [260.0, row_height],
+                            egui::Label::new(planned.destination.display().to_string()).truncate(),
+                        );
+                        ui.add_sized(
+                            [70.0, row_height],
+                            egui::Label::new(mover::format_size(planned.size)),
+                        );
+                        if let Some(index) = planned.rule {
+                            let name = preview.plan.rules.get(index).map_or("", String::as_str);
+                            // Numbered like the rules table, as names can repeat.
+                            ui.add_sized(
+                                [100.0, row_height],
+                                egui::Label::new(format!("{}. {}", index + 1, name)).truncate(),
+                            )
+                            .on_hover_text("Rule that claimed this file");
                         }
-                        Err(e) => {
-                            let _ = sender.send(format!(
-                                "Error moving {}: {}\n",
-                                file_path.display(),
-                                e
-                            ));
+                        if let Some(owner) = preview.owners.get(row) {
+                            ui.add_sized(
+                                [80.0, row_height],
+                                egui::Label::new(owner.as_str()).truncate(),
+                            );
                         }
-                    }
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut preview.tags[row])
+                                .hint_text("tags")
+                                .desired_width(140.0),
+                        );
+                        if response.lost_focus() {
+                            let tags = tags::parse_tags(&preview.tags[row]);
+                            edited = Some((planned.source.clone(), tags));
+                        }
+                    });
+                }
+            });
+        if let Some((path, tags)) = edited {
+            self.tag_writer.set_tags(ui.ctx(), path, tags);
+        }
+        if close {
+            self.preview = None;
+        }
+    }
+
+    /// The main tab: pick input, filters and output, then move.
+    fn move_tab_ui(&mut self, ui: &mut egui::Ui) {
+        self.profiles_ui(ui);
+        ui.separator();
+
+        // Input type selection.
+        ui.horizontal(|ui| {
+            ui.label("Input Type:");
+            ui.radio_value(&mut self.input_type, InputType::File, "File");
+            ui.radio_value(&mut self.input_type, InputType::Directory, "Directory");
+        });
+
+        // Input path.
+        ui.horizontal(|ui| {
+            ui.label("Input Path:");
+            ui.text_edit_singleline(&mut self.input_path);
+            if ui.button("Browse").clicked() {
+                let selected = if self.input_type == InputType::File {
+                    FileDialog::new().pick_file()
                 } else {
-                    let _ = sender.send(format!(
-                        "Warning: Skipping file with invalid name: {}\n",
-                        file_path.display()
-                    ));
+                    FileDialog::new().pick_folder()
+                };
+                if let Some(path) = selected {
+                    self.input_path = path.display().to_string();
                 }
             }
+        });
+
+        // Everything is a Windows tool, so the option is hidden elsewhere.
+        if cfg!(windows) && self.input_type == InputType::Directory {
+            ui.checkbox(
+                &mut self.use_index,
+                "Find files with the Everything index (needs es.exe on the PATH)",
+            );
         }
-    } else {
-        // Input is a single file.
-        let file_path = PathBuf::from(&input_path);
-        if !file_path.is_file() {
-            let _ = sender.send(format!("{} is not a valid file.\n", file_path.display()));
-            return Err(format!("{} is not a valid file.", file_path.display()).into());
-        }
-        let should_move = if filter_exts.is_empty() {
-            true
-        } else if let Some(ext) = file_path.extension().and_then(|s| s.to_str()) {
-            filter_exts.contains(&ext.to_lowercase())
+
+        // Extensions field. Rules bring their own extensions, so it's unused then.
+        ui.add_enabled_ui(!self.use_rules, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Extensions (comma-separated, e.g., pdf, jpg, png):");
+                ui.text_edit_singleline(&mut self.extensions);
+            });
+        });
+
+        // Extension alias groups, used by both the extensions field and the rules.
+        ui.horizontal(|ui| {
+            ui.label("Extension aliases (e.g., jpg/jpeg, tif/tiff):");
+            ui.text_edit_singleline(&mut self.aliases);
+        });
+
+        // Sorting rules.
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.use_rules, "Sort into folders using rules");
+            if ui.button("Downloads cleanup").clicked() {
+                self.load_downloads_cleanup();
+            }
+        });
+        if self.use_rules {
+            self.rules_ui(ui);
+        }
+
+        // File owners are a Unix concept, so the filter is hidden elsewhere.
+        if cfg!(unix) {
+            ui.horizontal(|ui| {
+                ui.label("Owner:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.owner)
+                        .hint_text("any user")
+                        .desired_width(100.0),
+                )
+                .on_hover_text("Only move files owned by this user name or UID");
+            });
+        }
+
+        // Updated as the filters above are edited.
+        let settings = self.settings();
+        self.match_counter.update(ui.ctx(), &settings);
+        if let Some(text) = self.match_counter.text() {
+            ui.label(text);
+        }
+
+        // Tagging files picked from a file dialog; tags can also be
+        // edited in the preview.
+        ui.horizontal(|ui| {
+            ui.label("Tag:");
+            ui.add(egui::TextEdit::singleline(&mut self.tag_to_apply).desired_width(100.0));
+            if ui.button("Tag Files...").clicked() {
+                self.tag_files(ui.ctx());
+            }
+        });
+
+        self.rename_fields_ui(ui);
+
+        // Output directory.
+        ui.horizontal(|ui| {
+            ui.label("Output Directory:");
+            ui.text_edit_singleline(&mut self.output_path);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.output_path = path.display().to_string();
+                }
+            }
+        });
+
+        ui.checkbox(
+            &mut self.skip_duplicates,
+            "Skip files identical to one already in the output directory",
+        );
+        ui.checkbox(
+            &mut self.copy,
+            "Copy instead of move (instant where the file system can clone files)",
+        );
+
+        // Shares are reached through UNC paths, which only Windows logs into.
+        if cfg!(windows) {
+            ui.collapsing("Network share login", |ui| {
+                ui.checkbox(
+                    &mut self.connect_shares,
+                    r"Log into \\server\share paths before moving",
+                );
+                ui.add_enabled_ui(self.connect_shares, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Username:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.share_credentials.username)
+                                .hint_text("stored credentials")
+                                .desired_width(140.0),
+                        );
+                        ui.label("Password:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.share_credentials.password)
+                                .password(true)
+                                .desired_width(140.0),
+                        );
+                    });
+                    ui.label("Leave the username empty to use the credentials Windows has stored.");
+                });
+            });
+        }
+
+        // Protected locations, hidden by default since they rarely need changing.
+        ui.collapsing("Protected paths", |ui| {
+            ui.label("One per line. ~ is your home folder; a trailing * also protects everything beneath.");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.protected_paths)
+                    .desired_rows(4)
+                    .desired_width(400.0),
+            );
+        });
+
+        // Button to start moving files. The input is scanned first and the
+        // move only happens once the user confirms the summary.
+        // "Preview" runs the same scan but only lists what would happen.
+        ui.horizontal(|ui| {
+            let move_clicked = ui
+                .button(if self.copy {
+                    "Copy Files"
+                } else {
+                    "Move Files"
+                })
+                .clicked();
+            let preview_clicked = ui.button("Preview").clicked();
+            if (move_clicked || preview_clicked) && !self.is_moving {
+                self.log.clear();
+                self.scan_for_preview = preview_clicked;
+                let settings = self.settings();
+                self.scan_settings = settings.clone();
+                self.spawn_worker(ui.ctx(), move |reporter| {
+                    let _ = mover::scan_thread(settings, reporter);
+                });
+            }
+            // A breakdown of what is in the input folder, to help pick filters.
+            if ui
+                .add_enabled(
+                    self.input_type == InputType::Directory && !self.is_moving,
+                    egui::Button::new("Disk Usage"),
+                )
+                .clicked()
+            {
+                let folder = PathBuf::from(&self.input_path);
+                let categories = if self.use_rules {
+                    self.rules.clone()
+                } else {
+                    rules::downloads_cleanup()
+                };
+                let aliases = self.aliases.clone();
+                self.log.clear();
+                self.spawn_worker(ui.ctx(), move |reporter| {
+                    usage::scan_thread(folder, categories, aliases, reporter)
+                });
+            }
+        });
+
+        self.preview_ui(ui);
+        self.usage_ui(ui);
+
+        ui.collapsing("History", |ui| self.history_ui(ui));
+    }
+
+    /// Disk usage of the input folder by extension or category, largest
+    /// first, with a bar showing each group's share.
+    fn usage_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(usage) = &self.usage else {
+            return;
+        };
+        let mut close = false;
+        let mut add_extension = None;
+        ui.horizontal(|ui| {
+            ui.strong(format!(
+                "Disk usage of {}: {}",
+                usage.folder.display(),
+                mover::format_size(usage.total_bytes)
+            ));
+            ui.radio_value(&mut self.usage_by_category, false, "By extension");
+            ui.radio_value(&mut self.usage_by_category, true, "By category");
+            close = ui.button("Close").clicked();
+        });
+        let groups = if self.usage_by_category {
+            &usage.by_category
         } else {
-            false
+            &usage.by_extension
         };
-        if should_move {
-            if let Some(file_name) = file_path.file_name() {
-                let mut dest_path = output_dir.join(file_name);
-                let mut counter = 1;
-                while dest_path.exists() {
-                    let stem = file_path
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("file");
-                    let new_name = if let Some(extension) = file_path.extension().and_then(|s| s.to_str()) {
-                        format!("{}_{}.{}", stem, counter, extension)
-                    } else {
-                        format!("{}_{}", stem, counter)
-                    };
-                    dest_path = output_dir.join(new_name);
-                    counter += 1;
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical()
+            .id_salt("usage")
+            .max_height(250.0)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, groups.len(), |ui, rows| {
+                for group in &groups[rows] {
+                    ui.horizontal(|ui| {
+                        let name = if group.name.is_empty() {
+                            "(no extension)"
+                        } else {
+                            &group.name
+                        };
+                        ui.add_sized([120.0, row_height], egui::Label::new(name).truncate());
+                        ui.add_sized(
+                            [80.0, row_height],
+                            egui::Label::new(format!("{} files", group.files)),
+                        );
+                        ui.add_sized(
+                            [70.0, row_height],
+                            egui::Label::new(mover::format_size(group.bytes)),
+                        );
+                        let share = group.bytes as f32 / usage.total_bytes.max(1) as f32;
+                        ui.add(
+                            egui::ProgressBar::new(share)
+                                .desired_width(200.0)
+                                .text(format!("{:.1}%", share * 100.0)),
+                        );
+                        if !self.usage_by_category
+                            && !group.name.is_empty()
+                            && ui
+                                .small_button("+")
+                                .on_hover_text("Add to the extensions filter")
+                                .clicked()
+                        {
+                            add_extension = Some(group.name.clone());
+                        }
+                    });
+                }
+            });
+        if let Some(extension) = add_extension {
+            if !self.extensions.trim().is_empty() {
+                self.extensions.push_str(", ");
+            }
+            self.extensions.push_str(&extension);
+        }
+        if close {
+            self.usage = None;
+        }
+    }
+
+    /// Template and character replacements for new file names.
+    fn rename_fields_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Rename to:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.rename_template)
+                    .hint_text("keep names")
+                    .desired_width(200.0),
+            )
+            .on_hover_text(rename::TEMPLATE_HELP);
+            ui.label("Replace:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.rename_replacements)
+                    .hint_text("from=to, ...")
+                    .desired_width(120.0),
+            );
+        });
+    }
+
+    /// Tool tab that renames files where they are, with the same template
+    /// and replacements as the mover.
+    fn rename_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Folder:");
+            ui.text_edit_singleline(&mut self.renamer.folder);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.renamer.folder = path.display().to_string();
+                }
+            }
+            ui.checkbox(&mut self.renamer.recursive, "Include sub-folders");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Extensions:");
+            ui.add(egui::TextEdit::singleline(&mut self.renamer.extensions).hint_text("all files"));
+        });
+        self.rename_fields_ui(ui);
+
+        let tab = &mut self.renamer;
+        let mut preview = false;
+        let mut rename = false;
+        let mut undo = false;
+        ui.horizontal(|ui| {
+            preview = ui
+                .add_enabled(!self.is_moving, egui::Button::new("Preview"))
+                .clicked();
+            rename = ui
+                .add_enabled(
+                    !self.is_moving && !tab.planned.is_empty(),
+                    egui::Button::new("Rename"),
+                )
+                .clicked();
+            undo = ui
+                .add_enabled(
+                    !self.is_moving && !tab.done.is_empty(),
+                    egui::Button::new("Undo"),
+                )
+                .clicked();
+        });
+
+        if !tab.planned.is_empty() {
+            let row_height = ui.spacing().interact_size.y;
+            egui::ScrollArea::both()
+                .id_salt("renames")
+                .max_height(250.0)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, tab.planned.len(), |ui, rows| {
+                    for planned in &tab.planned[rows] {
+                        let name = |path: &Path| {
+                            path.file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .into_owned()
+                        };
+                        ui.horizontal(|ui| {
+                            ui.add_sized(
+                                [260.0, row_height],
+                                egui::Label::new(name(&planned.source)).truncate(),
+                            )
+                            .on_hover_text(planned.source.display().to_string());
+                            ui.label("->");
+                            ui.add_sized(
+                                [260.0, row_height],
+                                egui::Label::new(name(&planned.destination)).truncate(),
+                            );
+                            if planned.collision {
+                                ui.label("(numbered, name taken)");
+                            }
+                        });
+                    }
+                });
+        }
+
+        if preview {
+            let folder = PathBuf::from(&tab.folder);
+            let recursive = tab.recursive;
+            let aliases = filters::parse_aliases(&self.aliases);
+            let extensions = filters::parse_extensions(&tab.extensions, &aliases);
+            let renamer = Renamer::new(&self.rename_template, &self.rename_replacements);
+            let protected_paths = self.protected_paths.clone();
+            tab.planned.clear();
+            self.log.clear();
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                rename::scan_thread(
+                    folder,
+                    recursive,
+                    extensions,
+                    renamer,
+                    protected_paths,
+                    reporter,
+                )
+            });
+        } else if rename {
+            let planned = std::mem::take(&mut tab.planned);
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                rename::rename_thread(planned, false, reporter)
+            });
+        } else if undo {
+            let planned = rename::undo_plan(&tab.done);
+            tab.done.clear();
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                rename::rename_thread(planned, true, reporter)
+            });
+        }
+    }
+
+    /// Tool tab that lists the differences between two folders and can
+    /// bring missing or changed files over to either side.
+    fn compare_ui(&mut self, ui: &mut egui::Ui) {
+        let tab = &mut self.compare;
+        for (label, path) in [("Left:", &mut tab.left), ("Right:", &mut tab.right)] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                ui.text_edit_singleline(path);
+                if ui.button("Browse").clicked() {
+                    if let Some(folder) = FileDialog::new().pick_folder() {
+                        *path = folder.display().to_string();
+                    }
                 }
-                match fs::rename(&file_path, &dest_path) {
-                    Ok(_) => {
-                        let _ = sender.send(format!(
-                            "Moved: {} -> {}\n",
-                            file_path.display(),
-                            dest_path.display()
-                        ));
+            });
+        }
+
+        let compare = ui
+            .add_enabled(!self.is_moving, egui::Button::new("Compare"))
+            .clicked();
+        let mut transfer = None;
+        if let Some(comparison) = &tab.comparison {
+            ui.label(format!(
+                "{} only on the left, {} only on the right, {} different.",
+                comparison.only_left(),
+                comparison.only_right(),
+                comparison.changed()
+            ));
+            let row_height = ui.spacing().interact_size.y;
+            egui::ScrollArea::both()
+                .id_salt("compare")
+                .max_height(250.0)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, comparison.differences.len(), |ui, rows| {
+                    for difference in &comparison.differences[rows] {
+                        let size =
+                            |size: Option<u64>| size.map(mover::format_size).unwrap_or_default();
+                        let status = match (difference.left_size, difference.right_size) {
+                            (Some(_), None) => "only left",
+                            (None, Some(_)) => "only right",
+                            _ => "different",
+                        };
+                        ui.horizontal(|ui| {
+                            ui.add_sized(
+                                [360.0, row_height],
+                                egui::Label::new(difference.relative.display().to_string())
+                                    .truncate(),
+                            );
+                            ui.add_sized(
+                                [70.0, row_height],
+                                egui::Label::new(size(difference.left_size)),
+                            );
+                            ui.add_sized(
+                                [70.0, row_height],
+                                egui::Label::new(size(difference.right_size)),
+                            );
+                            ui.label(status);
+                        });
                     }
-                    Err(e) => {
-                        let _ = sender.send(format!(
-                            "Error moving {}: {}\n",
-                            file_path.display(),
-                            e
-                        ));
+                });
+            ui.horizontal(|ui| {
+                let enabled = !self.is_moving && !comparison.differences.is_empty();
+                for (text, to_right, copy) in [
+                    ("Copy to Right", true, true),
+                    ("Move to Right", true, false),
+                    ("Copy to Left", false, true),
+                    ("Move to Left", false, false),
+                ] {
+                    if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+                        transfer = Some((to_right, copy));
                     }
                 }
+            });
+        }
+
+        if compare {
+            let left = PathBuf::from(&tab.left);
+            let right = PathBuf::from(&tab.right);
+            let protected_paths = self.protected_paths.clone();
+            tab.comparison = None;
+            self.log.clear();
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                let _ = compare::compare_thread(left, right, protected_paths, reporter);
+            });
+        } else if let Some((to_right, copy)) = transfer {
+            // Goes through the same confirmation as a regular move. The
+            // comparison is out of date once files are transferred.
+            if let Some(comparison) = tab.comparison.take() {
+                self.pending_plan = Some(comparison.plan(to_right, copy));
+            }
+        }
+    }
+
+    /// Tool tab that finds and removes empty folders.
+    fn empty_folders_ui(&mut self, ui: &mut egui::Ui) {
+        let tab = &mut self.empty_folders;
+        ui.horizontal(|ui| {
+            ui.label("Folder:");
+            ui.text_edit_singleline(&mut tab.folder);
+            if ui.button("Browse").clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    tab.folder = path.display().to_string();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut tab.ignore_junk, "Also count folders containing only:");
+            ui.add_enabled(
+                tab.ignore_junk,
+                egui::TextEdit::singleline(&mut tab.junk_files),
+            );
+        });
+
+        let mut scan = false;
+        let mut remove = false;
+        let mut confirmed = false;
+        let mut undo = false;
+        ui.horizontal(|ui| {
+            scan = ui
+                .add_enabled(!self.is_moving, egui::Button::new("Find Empty Folders"))
+                .clicked();
+            remove = ui
+                .add_enabled(
+                    !self.is_moving && !tab.found.is_empty(),
+                    egui::Button::new("Move to Recycle Bin"),
+                )
+                .clicked();
+            if empty_dirs::CAN_RESTORE {
+                undo = ui
+                    .add_enabled(
+                        !self.is_moving && !tab.trashed.is_empty(),
+                        egui::Button::new("Undo"),
+                    )
+                    .clicked();
+            }
+        });
+
+        ui.label(format!("{} empty folders found.", tab.found.len()));
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::both()
+            .id_salt("empty_folders")
+            .max_height(250.0)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, tab.found.len(), |ui, rows| {
+                for dir in &tab.found[rows] {
+                    ui.add(egui::Label::new(dir.display().to_string()).extend());
+                }
+            });
+
+        // Nothing is trashed until the user has confirmed, like moves.
+        if remove {
+            tab.confirm_trash = true;
+        }
+        if tab.confirm_trash {
+            egui::Modal::new(egui::Id::new("confirm_trash")).show(ui.ctx(), |ui| {
+                ui.heading("Confirm Cleanup");
+                ui.label(format!(
+                    "{} empty folders will be moved to the recycle bin.",
+                    tab.found.len()
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Move to Recycle Bin").clicked() {
+                        confirmed = true;
+                        tab.confirm_trash = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        tab.confirm_trash = false;
+                    }
+                });
+            });
+        }
+
+        if scan {
+            let root = PathBuf::from(&tab.folder);
+            let junk_files = tab.junk_files();
+            let protected_paths = self.protected_paths.clone();
+            tab.found.clear();
+            self.log.clear();
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                empty_dirs::scan_thread(root, junk_files, protected_paths, reporter)
+            });
+        } else if confirmed {
+            let found = std::mem::take(&mut tab.found);
+            let junk_files = tab.junk_files();
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                empty_dirs::trash_thread(found, junk_files, reporter)
+            });
+        } else if undo {
+            let trashed = std::mem::take(&mut tab.trashed);
+            self.spawn_worker(ui.ctx(), move |reporter| {
+                empty_dirs::restore_thread(trashed, reporter)
+            });
+        }
+    }
+
+    /// Table of the most recent runs.
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Refresh").clicked() {
+            self.refresh_history();
+        }
+        if self.history_runs.is_empty() {
+            ui.label("No runs recorded yet.");
+            return;
+        }
+        egui::Grid::new("history_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Started");
+                ui.label("From");
+                ui.label("To");
+                ui.label("Moved");
+                ui.label("Failed");
+                ui.label("Size");
+                ui.end_row();
+                for run in &self.history_runs {
+                    let started = chrono::DateTime::parse_from_rfc3339(&run.started_at)
+                        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_else(|_| run.started_at.clone());
+                    ui.label(started);
+                    ui.label(&run.input_path);
+                    ui.label(&run.output_path);
+                    ui.label(run.moved.to_string());
+                    ui.label(run.failed.to_string());
+                    ui.label(mover::format_size(run.bytes_moved));
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn settings(&self) -> MoveSettings {
+        MoveSettings {
+            input_path: self.input_path.clone(),
+            output_path: self.output_path.clone(),
+            extensions: self.extensions.clone(),
+            aliases: self.aliases.clone(),
+            input_type: self.input_type,
+            rules: self.use_rules.then(|| self.rules.clone()),
+            protected_paths: self.protected_paths.clone(),
+            skip_duplicates: self.skip_duplicates,
+            copy: self.copy,
+            use_index: self.use_index,
+            owner: self.owner.clone(),
+            share_login: self.connect_shares.then(|| self.share_credentials.clone()),
+            rename_template: self.rename_template.clone(),
+            rename_replacements: self.rename_replacements.clone(),
+        }
+    }
+
+    /// Starts a background thread, routing its messages into the log.
+    fn spawn_worker(&mut self, ctx: &egui::Context, work: impl FnOnce(Reporter) + Send + 'static) {
+        let (tx, rx) = mpsc::channel::<WorkerMessage>();
+        self.log_rx = Some(rx);
+        self.is_moving = true;
+        self.progress = None;
+        let reporter = Reporter::new(tx, ctx.clone());
+        thread::spawn(move || work(reporter));
+    }
+
+    /// Modal summarizing the pending plan, so nothing moves until the user
+    /// has seen what is about to happen.
+    fn confirm_ui(&mut self, ctx: &egui::Context) {
+        let Some(plan) = &self.pending_plan else {
+            return;
+        };
+        let mut confirmed = None;
+        egui::Modal::new(egui::Id::new("confirm_move")).show(ctx, |ui| {
+            ui.heading(if plan.copy {
+                "Confirm Copy"
+            } else {
+                "Confirm Move"
+            });
+            if plan.moves.is_empty() {
+                ui.label("No matching files were found.");
             } else {
-                let _ = sender.send(format!(
-                    "Warning: Skipping file with invalid name: {}\n",
-                    file_path.display()
+                ui.label(format!(
+                    "{} files ({}) will be {} from {} to {}.",
+                    plan.moves.len(),
+                    mover::format_size(plan.total_bytes()),
+                    if plan.copy { "copied" } else { "moved" },
+                    plan.input_path,
+                    plan.output_path
+                ));
+                ui.label(format!(
+                    "{} collisions expected (those files get a numbered name).",
+                    plan.collisions()
+                ));
+            }
+            if !plan.duplicates.is_empty() {
+                ui.label(format!(
+                    "{} files are identical to ones already in the destination and stay where they are.",
+                    plan.duplicates.len()
                 ));
             }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!plan.moves.is_empty(), egui::Button::new("Confirm"))
+                    .clicked()
+                {
+                    confirmed = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    confirmed = Some(false);
+                }
+            });
+        });
+        match confirmed {
+            Some(true) => {
+                if let Some(plan) = self.pending_plan.take() {
+                    // A preview made with the same settings is checked against
+                    // what the run actually does. Any other preview would
+                    // differ from the run by design. It is out of date afterwards.
+                    let dry_run = self
+                        .preview
+                        .take()
+                        .filter(|preview| preview.settings == self.scan_settings)
+                        .map(|preview| DryRunCheck::new(&preview.plan, &plan));
+                    self.spawn_worker(ctx, move |reporter| {
+                        mover::execute_thread(plan, None, dry_run, reporter)
+                    });
+                }
+            }
+            Some(false) => {
+                self.pending_plan = None;
+                self.log.push(LogEntry::info("Move cancelled."));
+            }
+            None => {}
+        }
+    }
+
+    /// Modal offering to resume a job that was interrupted by a crash or by
+    /// closing the app mid-run.
+    fn resume_ui(&mut self, ctx: &egui::Context) {
+        let Some((plan, completed)) = &self.interrupted_job else {
+            return;
+        };
+        let mut resume = None;
+        egui::Modal::new(egui::Id::new("resume_job")).show(ctx, |ui| {
+            ui.heading("Resume Interrupted Move?");
+            ui.label(format!(
+                "A previous move from {} to {} stopped after {} of {} files.",
+                plan.input_path,
+                plan.output_path,
+                completed,
+                plan.moves.len()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Resume").clicked() {
+                    resume = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    resume = Some(false);
+                }
+            });
+        });
+        match resume {
+            Some(true) => {
+                if let Some((plan, completed)) = self.interrupted_job.take() {
+                    self.log.push(LogEntry::info(format!(
+                        "Resuming interrupted move at file {} of {}.",
+                        completed + 1,
+                        plan.moves.len()
+                    )));
+                    self.spawn_worker(ctx, move |reporter| {
+                        mover::execute_thread(plan, Some(completed), None, reporter)
+                    });
+                }
+            }
+            Some(false) => {
+                self.interrupted_job = None;
+                job::clear();
+            }
+            None => {}
         }
     }
-    let _ = sender.send("Moving completed successfully.\n".to_string());
-    Ok(())
+
+    /// Loads the built-in "Downloads cleanup" rules. Empty path fields are
+    /// prefilled with the user's Downloads folder and a "Sorted" folder in it.
+    fn load_downloads_cleanup(&mut self) {
+        self.rules = rules::downloads_cleanup();
+        self.use_rules = true;
+        self.input_type = InputType::Directory;
+        if let Some(downloads) = dirs::download_dir() {
+            if self.input_path.is_empty() {
+                self.input_path = downloads.display().to_string();
+            }
+            if self.output_path.is_empty() {
+                self.output_path = downloads.join("Sorted").display().to_string();
+            }
+        }
+    }
+
+    /// Editable table of the sorting rules.
+    fn rules_ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        let mut swap = None;
+        let count = self.rules.len();
+        ui.label("Rules are checked from the top; the first one that matches a file wins.");
+        egui::Grid::new("rules_grid").striped(true).show(ui, |ui| {
+            ui.label("#");
+            ui.label("Name");
+            ui.label("Extensions (empty = any)");
+            ui.label("Tags (any of)");
+            ui.label("Folder");
+            ui.end_row();
+            for (index, rule) in self.rules.iter_mut().enumerate() {
+                ui.label((index + 1).to_string());
+                ui.add(egui::TextEdit::singleline(&mut rule.name).desired_width(100.0));
+                ui.add(egui::TextEdit::singleline(&mut rule.extensions).desired_width(260.0));
+                ui.add(egui::TextEdit::singleline(&mut rule.tags).desired_width(100.0));
+                ui.add(egui::TextEdit::singleline(&mut rule.folder).desired_width(100.0));
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                        swap = Some(index - 1);
+                    }
+                    if ui
+                        .add_enabled(index + 1 < count, egui::Button::new("Down"))
+                        .clicked()
+                    {
+                        swap = Some(index);
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some(index) = swap {
+            self.rules.swap(index, index + 1);
+        }
+        if let Some(index) = remove {
+            self.rules.remove(index);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add Rule").clicked() {
+                self.rules.push(Rule::new("New rule", "", ""));
+            }
+            if ui.button("Load \"Downloads cleanup\" preset").clicked() {
+                self.load_downloads_cleanup();
+            }
+        });
+    }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Tag changes are saved in the background too.
+        for entry in self.tag_writer.results() {
+            self.log.push(entry);
+        }
         // Drain any log messages coming from the background thread.
         if let Some(rx) = &self.log_rx {
             loop {
                 match rx.try_recv() {
-                    Ok(msg) => self.log.push_str(&msg),
+                    Ok(WorkerMessage::Log(entry)) => self.log.push(entry),
+                    Ok(WorkerMessage::Plan(plan)) if self.scan_for_preview => {
+                        self.preview = Some(Preview::new(plan, self.scan_settings.clone()))
+                    }
+                    Ok(WorkerMessage::Plan(plan)) => self.pending_plan = Some(plan),
+                    Ok(WorkerMessage::Progress { done, total }) => {
+                        self.progress = Some((done, total))
+                    }
+                    Ok(WorkerMessage::Usage(usage)) => self.usage = Some(usage),
+                    Ok(WorkerMessage::Renames(planned)) => self.renamer.planned = planned,
+                    Ok(WorkerMessage::Renamed(done)) => self.renamer.done = done,
+                    Ok(WorkerMessage::Comparison(comparison)) => {
+                        self.compare.comparison = Some(comparison)
+                    }
+                    Ok(WorkerMessage::EmptyDirs(found)) => self.empty_folders.found = found,
+                    Ok(WorkerMessage::Trashed(dirs)) => self.empty_folders.trashed = dirs,
                     Err(mpsc::TryRecvError::Empty) => break,
                     Err(mpsc::TryRecvError::Disconnected) => {
                         self.is_moving = false;
                         self.log_rx = None;
+                        // Files may have been moved, renamed or removed.
+                        self.match_counter.invalidate();
+                        self.refresh_history();
                         break;
                     }
                 }
@@ -216,80 +1369,100 @@ impl eframe::App for MyApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("File Mover");
 
-            // Input type selection.
-            ui.horizontal(|ui| {
-                ui.label("Input Type:");
-                ui.radio_value(&mut self.input_type, InputType::File, "File");
-                ui.radio_value(&mut self.input_type, InputType::Directory, "Directory");
-            });
-
-            // Input path.
+            // Tool tabs.
             ui.horizontal(|ui| {
-                ui.label("Input Path:");
-                ui.text_edit_singleline(&mut self.input_path);
-                if ui.button("Browse").clicked() {
-                    let selected = if self.input_type == InputType::File {
-                        FileDialog::new().pick_file()
-                    } else {
-                        FileDialog::new().pick_folder()
-                    };
-                    if let Some(path) = selected {
-                        self.input_path = path.display().to_string();
-                    }
-                }
+                ui.selectable_value(&mut self.tab, Tab::Move, "Move Files");
+                ui.selectable_value(&mut self.tab, Tab::Rename, "Rename");
+                ui.selectable_value(&mut self.tab, Tab::Compare, "Compare Folders");
+                ui.selectable_value(&mut self.tab, Tab::EmptyFolders, "Empty Folders");
             });
+            ui.separator();
 
-            // Extensions field.
-            ui.horizontal(|ui| {
-                ui.label("Extensions (comma-separated, e.g., pdf, jpg, png):");
-                ui.text_edit_singleline(&mut self.extensions);
-            });
+            match self.tab {
+                Tab::Move => self.move_tab_ui(ui),
+                Tab::Rename => self.rename_ui(ui),
+                Tab::Compare => self.compare_ui(ui),
+                Tab::EmptyFolders => self.empty_folders_ui(ui),
+            }
 
-            // Output directory.
-            ui.horizontal(|ui| {
-                ui.label("Output Directory:");
-                ui.text_edit_singleline(&mut self.output_path);
-                if ui.button("Browse").clicked() {
-                    if let Some(path) = FileDialog::new().pick_folder() {
-                        self.output_path = path.display().to_string();
+            if self.is_moving {
+                match self.progress {
+                    Some((done, total)) => {
+                        ui.add(
+                            egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                                .text(format!("{} / {} files", done, total)),
+                        );
+                    }
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Scanning...");
+                        });
                     }
                 }
-            });
-
-            // Button to start moving files.
-            if ui.button("Move Files").clicked() && !self.is_moving {
-                self.log.clear();
-                let input_path = self.input_path.clone();
-                let output_path = self.output_path.clone();
-                let extensions = self.extensions.clone();
-                let input_type = self.input_type;
-                let (tx, rx) = mpsc::channel::<String>();
-                self.log_rx = Some(rx);
-                self.is_moving = true;
-                thread::spawn(move || {
-                    let _ = move_files_thread(input_path, output_path, extensions, input_type, tx);
-                });
             }
 
             ui.separator();
 
-            // Log output in a scrollable area that sticks to the bottom.
+            // Log output in a scrollable area that sticks to the bottom. Only the
+            // visible rows are laid out, so a huge log stays responsive.
             ui.label("Log:");
-            egui::ScrollArea::vertical()
+            if self.log.dropped() > 0 {
+                ui.label(format!(
+                    "({} older entries not shown, only the last {} are kept)",
+                    self.log.dropped(),
+                    logging::MAX_ENTRIES
+                ));
+            }
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
                 .max_height(300.0)
                 .stick_to_bottom(true)
-                .show(ui, |ui| {
-                    ui.add(
-                        egui::TextEdit::multiline(&mut self.log)
-                            .desired_rows(20)
-                            .desired_width(600.0),
-                    );
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, self.log.len(), |ui, rows| {
+                    for entry in rows.filter_map(|row| self.log.get(row)) {
+                        let text = egui::RichText::new(entry.to_string()).monospace();
+                        let text = match entry.level {
+                            Level::Info => text,
+                            Level::Warning => text.color(ui.visuals().warn_fg_color),
+                            Level::Error => text.color(ui.visuals().error_fg_color),
+                        };
+                        ui.add(egui::Label::new(text).extend());
+                    }
                 });
         });
+
+        self.confirm_ui(ctx);
+        self.resume_ui(ctx);
     }
 }
 
 fn main() {
+    let args = match cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            cli::attach_console();
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    // Before anything reads or writes the app's files.
+    if args.portable {
+        storage::enable_portable();
+    }
+    // Scheduled runs move the files of a profile without opening a window.
+    if let (true, Some(name)) = (args.run, &args.profile) {
+        cli::attach_console();
+        let succeeded = match profiles::load(name) {
+            Ok(settings) => cli::run(settings),
+            Err(e) => {
+                eprintln!("Could not load the profile: {}", e);
+                false
+            }
+        };
+        std::process::exit(if succeeded { 0 } else { 1 });
+    }
+
     let icon_path = Path::new("icon.ico");
 
     let icon_data = if icon_path.exists() {
@@ -318,6 +1491,6 @@ fn main() {
     let _ = eframe::run_native(
         "File Mover",
         native_options,
-        Box::new(|_cc| Ok(Box::new(MyApp::default()))),
+        Box::new(|_cc| Ok(Box::new(MyApp::new(args.profile)))),
     );
-}
\ No newline at end of file
+}
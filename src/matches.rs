@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use walkdir::WalkDir;
+
+use crate::filters;
+use crate::mover::{self, InputType, MoveSettings};
+use crate::owner;
+use crate::protected;
+use crate::rules::{self, Rule};
+use crate::tags::{self, TagStore};
+
+/// How long the filters have to stay unchanged before the count is updated,
+/// so typing doesn't recount on every key.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The settings that decide which files match.
+#[derive(Clone, PartialEq)]
+struct Filters {
+    folder: String,
+    extensions: String,
+    aliases: String,
+    rules: Option<Vec<Rule>>,
+    owner: String,
+    /// Destinations inside the folder are skipped, like in a scan.
+    output: String,
+    protected_paths: String,
+}
+
+impl Filters {
+    fn new(settings: &MoveSettings) -> Self {
+        Self {
+            folder: settings.input_path.clone(),
+            extensions: settings.extensions.clone(),
+            aliases: settings.aliases.clone(),
+            rules: settings.rules.clone(),
+            owner: settings.owner.clone(),
+            output: settings.output_path.clone(),
+            protected_paths: settings.protected_paths.clone(),
+        }
+    }
+}
+
+struct ListedFile {
+    path: PathBuf,
+    size: u64,
+    owner: Option<u32>,
+}
+
+/// Every file under a folder, walked once and reused while only the
+/// filters change.
+struct Listing {
+    folder: String,
+    files: Vec<ListedFile>,
+}
+
+/// Walks the folder, or returns None once `cancel` is set.
+fn list_folder(folder: &str, cancel: &AtomicBool) -> Option<Listing> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(folder).into_iter().filter_map(|e| e.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        files.push(ListedFile {
+            size: metadata.len(),
+            owner: owner::uid_of(&metadata),
+            path: entry.into_path(),
+        });
+    }
+    Some(Listing {
+        folder: folder.to_string(),
+        files,
+    })
+}
+
+/// Number of matching files and their total size, or why they couldn't be
+/// counted.
+type Count = Result<(usize, u64), String>;
+
+/// What a count thread sends back: the listing it used, to be cached, and
+/// the count.
+struct Counted {
+    listing: Option<Arc<Listing>>,
+    count: Count,
+}
+
+/// A count running in the background.
+struct Counting {
+    filters: Filters,
+    /// Set when the count is no longer wanted, e.g. the folder was edited
+    /// again, to stop the thread early.
+    cancel: Arc<AtomicBool>,
+    /// True while the folder is walked, which is worth finishing if only
+    /// the other filters changed.
+    walking: bool,
+    rx: mpsc::Receiver<Counted>,
+}
+
+/// Counts the files the current filters would pick up, live as they are
+/// edited. The input folder is walked in the background once and the
+/// listing is cached, so changing filters only re-matches file names.
+#[derive(Default)]
+pub struct MatchCounter {
+    /// Filters as last seen, and when they last changed.
+    filters: Option<(Filters, Instant)>,
+    listing: Option<Arc<Listing>>,
+    counting: Option<Counting>,
+    /// The filters the count is for, and the count.
+    counted: Option<(Filters, Count)>,
+}
+
+impl MatchCounter {
+    /// Forgets the cached listing, e.g. after files were moved.
+    pub fn invalidate(&mut self) {
+        self.cancel();
+        self.listing = None;
+        self.counted = None;
+    }
+
+    fn cancel(&mut self) {
+        if let Some(counting) = self.counting.take() {
+            counting.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Called every frame with the current settings; updates the count once
+    /// the filters have settled.
+    pub fn update(&mut self, ctx: &egui::Context, settings: &MoveSettings) {
+        if let Some(counting) = &self.counting {
+            match counting.rx.try_recv() {
+                Ok(counted) => {
+                    if let Some(listing) = counted.listing {
+                        self.listing = Some(listing);
+                    }
+                    self.counted = Some((counting.filters.clone(), counted.count));
+                    self.counting = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                // The count panicked; it is retried below.
+                Err(mpsc::TryRecvError::Disconnected) => self.counting = None,
+            }
+        }
+        if settings.input_type != InputType::Directory {
+            self.cancel();
+            self.filters = None;
+            return;
+        }
+
+        let filters = Filters::new(settings);
+        match &self.filters {
+            Some((previous, _)) if *previous == filters => {}
+            _ => {
+                self.filters = Some((filters, Instant::now()));
+                ctx.request_repaint_after(DEBOUNCE);
+                return;
+            }
+        }
+        let Some((filters, changed_at)) = &self.filters else {
+            return;
+        };
+        if self
+            .counted
+            .as_ref()
+            .is_some_and(|(counted, _)| counted == filters)
+            || self
+                .counting
+                .as_ref()
+                .is_some_and(|counting| counting.filters == *filters)
+        {
+            return;
+        }
+        if changed_at.elapsed() < DEBOUNCE {
+            ctx.request_repaint_after(DEBOUNCE - changed_at.elapsed());
+            return;
+        }
+
+        // The listing of a walk of the same folder is reused once it is done.
+        if self
+            .counting
+            .as_ref()
+            .is_some_and(|counting| counting.walking && counting.filters.folder == filters.folder)
+        {
+            return;
+        }
+        // Anything else still running is for filters edited since.
+        let filters = filters.clone();
+        self.cancel();
+        let listing = self
+            .listing
+            .clone()
+            .filter(|listing| listing.folder == filters.folder);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        self.counting = Some(Counting {
+            filters: filters.clone(),
+            cancel: cancel.clone(),
+            walking: listing.is_none(),
+            rx,
+        });
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            if let Some(counted) = count_thread(&filters, listing, &cancel) {
+                let _ = tx.send(counted);
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// E.g. "Matches: 1,432 files, 8.2 GB". None for single-file input.
+    /// While typing, the previous count stays up until the new one is ready.
+    pub fn text(&self) -> Option<String> {
+        let (filters, _) = self.filters.as_ref()?;
+        Some(match &self.counted {
+            Some((counted, Ok((files, bytes)))) if counted.folder == filters.folder => format!(
+                "Matches: {} files, {}",
+                format_count(*files),
+                mover::format_size(*bytes)
+            ),
+            Some((counted, Err(e))) if counted.folder == filters.folder => {
+                format!("Matches: {}", e)
+            }
+            _ => "Matches: counting...".to_string(),
+        })
+    }
+}
+
+/// This function runs in a background thread. It walks the folder, unless
+/// its listing is given, and counts the matches. Returns None once `cancel`
+/// is set.
+fn count_thread(
+    filters: &Filters,
+    listing: Option<Arc<Listing>>,
+    cancel: &AtomicBool,
+) -> Option<Counted> {
+    let folder = Path::new(&filters.folder);
+    let refused = if !folder.is_dir() {
+        Some("no such folder".to_string())
+    } else {
+        // A scan would refuse it, and walking e.g. "/" takes ages.
+        protected::blocking_entry(folder, &filters.protected_paths, true)
+            .map(|_| "protected location".to_string())
+    };
+    if let Some(reason) = refused {
+        return Some(Counted {
+            listing: None,
+            count: Err(reason),
+        });
+    }
+    let listing = match listing {
+        Some(listing) => listing,
+        None => Arc::new(list_folder(&filters.folder, cancel)?),
+    };
+    let count = count(&listing, filters, cancel)?;
+    Some(Counted {
+        listing: Some(listing),
+        count,
+    })
+}
+
+/// Applies the same filters as a scan: the rules if given, otherwise the
+/// extensions, and the owner. Returns None once `cancel` is set.
+fn count(listing: &Listing, filters: &Filters, cancel: &AtomicBool) -> Option<Count> {
+    let owner_uid = match owner::parse_owner(&filters.owner) {
+        Ok(owner_uid) => owner_uid,
+        Err(e) => return Some(Err(e)),
+    };
+    let alias_groups = filters::parse_aliases(&filters.aliases);
+    let extensions = filters::parse_extensions(&filters.extensions, &alias_groups);
+    let compiled = filters
+        .rules
+        .as_deref()
+        .map(|rules| rules::compile(rules, &alias_groups));
+    let tag_map = if compiled.as_deref().is_some_and(rules::uses_tags) {
+        TagStore::open()
+            .and_then(|store| Ok(store.all()?))
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let folder = Path::new(&filters.folder);
+    let skipped: Vec<PathBuf> =
+        mover::nested_destinations(folder, Path::new(&filters.output), compiled.as_deref())
+            .into_iter()
+            .map(|relative| folder.join(relative))
+            .collect();
+
+    let mut files = 0;
+    let mut bytes = 0;
+    for file in &listing.files {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if owner_uid.is_some() && file.owner != owner_uid {
+            continue;
+        }
+        if skipped.iter().any(|dir| file.path.starts_with(dir)) {
+            continue;
+        }
+        let matches = match &compiled {
+            Some(compiled) => {
+                let file_tags = if tag_map.is_empty() {
+                    &[][..]
+                } else {
+                    tag_map
+                        .get(&tags::key(&file.path))
+                        .map_or(&[][..], Vec::as_slice)
+                };
+                rules::first_match(compiled, &file.path, file_tags).is_some()
+            }
+            None => filters::matches_extension(&extensions, &file.path),
+        };
+        if matches {
+            files += 1;
+            bytes += file.size;
+        }
+    }
+    Some(Ok((files, bytes)))
+}
+
+/// Formats a count with thousands separators, e.g. "1,432".
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
@@ -0,0 +1,947 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::compare::Comparison;
+use crate::copy;
+use crate::dry_run::DryRunCheck;
+use crate::everything;
+use crate::filters;
+use crate::hashing::HashCache;
+use crate::history::History;
+use crate::job;
+use crate::logging::LogEntry;
+use crate::network::{self, Credentials};
+use crate::owner;
+use crate::protected;
+use crate::rename::Renamer;
+use crate::rule_stats::RuleStats;
+use crate::rules::{self, CompiledRule, Rule};
+use crate::tags::{self, TagStore};
+use crate::usage::Usage;
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum InputType {
+    File,
+    #[default]
+    Directory,
+}
+
+/// Snapshot of the user's settings that a scan works from. Saved in
+/// profiles; fields missing from older profiles get their defaults.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MoveSettings {
+    pub input_path: String,
+    pub output_path: String,
+    /// Comma-separated list of file extensions (e.g., "pdf, jpg, png")
+    pub extensions: String,
+    /// Comma-separated groups of equivalent extensions (e.g., "jpg/jpeg, tif/tiff")
+    pub aliases: String,
+    pub input_type: InputType,
+    /// When given, files are sorted into the folder of the first matching
+    /// rule and `extensions` is ignored.
+    pub rules: Option<Vec<Rule>>,
+    /// Locations that may not be used as input or output, one per line.
+    /// Not saved in profiles: the app-wide list always applies.
+    #[serde(skip)]
+    pub protected_paths: String,
+    /// Leave files in place when an identical file (same content) already
+    /// exists under the same name at the destination.
+    pub skip_duplicates: bool,
+    /// Ask the Everything search index (Windows) for the file list instead
+    /// of walking the input folder.
+    pub use_index: bool,
+    /// Copy the files instead of moving them, leaving the originals in place.
+    pub copy: bool,
+    /// When given, network shares in the input and output paths are logged
+    /// into with these credentials before scanning (Windows only).
+    pub share_login: Option<Credentials>,
+    /// Only move files owned by this user name or UID (Unix only). Empty
+    /// moves files of any owner.
+    pub owner: String,
+    /// Template for the new file names, see `rename::TEMPLATE_HELP`. Empty
+    /// keeps the names.
+    pub rename_template: String,
+    /// Character replacements applied to the new names ("from=to, ...").
+    pub rename_replacements: String,
+}
+
+/// A single file the scan decided to move.
+#[derive(Serialize, Deserialize)]
+pub struct PlannedMove {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub size: u64,
+    /// True if a file with the same name was already there (or planned), so the
+    /// destination got a numbered name.
+    pub collision: bool,
+    /// Index in `Plan::rules` of the rule that claimed the file, when sorting
+    /// by rules. Rule names needn't be unique.
+    #[serde(default)]
+    pub rule: Option<usize>,
+    /// UID of the file's owner when it was scanned (Unix only).
+    #[serde(default)]
+    pub owner: Option<u32>,
+}
+
+/// The result of a scan: everything that will happen if the user confirms.
+#[derive(Serialize, Deserialize)]
+pub struct Plan {
+    pub input_path: String,
+    pub output_path: String,
+    pub moves: Vec<PlannedMove>,
+    /// Files left in place because an identical copy is already at the destination.
+    #[serde(default)]
+    pub duplicates: Vec<PathBuf>,
+    /// Copy the files instead of moving them, leaving the originals in place.
+    #[serde(default)]
+    pub copy: bool,
+    /// Names of all rules, in order, when sorting by rules.
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// The tags each planned file had when it was scanned, comma-separated,
+    /// for the preview.
+    #[serde(skip)]
+    pub tags: Vec<String>,
+}
+
+impl Plan {
+    pub fn total_bytes(&self) -> u64 {
+        self.moves.iter().map(|m| m.size).sum()
+    }
+
+    pub fn collisions(&self) -> usize {
+        self.moves.iter().filter(|m| m.collision).count()
+    }
+}
+
+/// Messages sent from the background threads to the GUI.
+pub enum WorkerMessage {
+    Log(LogEntry),
+    /// Sent once a scan finishes, to be confirmed by the user.
+    Plan(Plan),
+    /// Disk usage of the input folder, by kind of file.
+    Usage(Usage),
+    /// Differences found by the folder compare tool.
+    Comparison(Comparison),
+    /// Renames planned by the batch renamer.
+    Renames(Vec<PlannedMove>),
+    /// Renames the batch renamer carried out, kept for undo.
+    Renamed(Vec<PlannedMove>),
+    /// Empty folders found by the cleanup tool.
+    EmptyDirs(Vec<PathBuf>),
+    /// Empty folders the cleanup tool moved to the trash, kept for undo.
+    Trashed(Vec<PathBuf>),
+    /// Number of files processed so far out of the whole run.
+    Progress {
+        done: usize,
+        total: usize,
+    },
+}
+
+/// Sends messages from a background thread to the GUI and asks it to repaint,
+/// so progress shows up in real time even when the window is idle.
+#[derive(Clone)]
+pub struct Reporter {
+    sender: mpsc::Sender<WorkerMessage>,
+    ctx: egui::Context,
+}
+
+impl Reporter {
+    pub fn new(sender: mpsc::Sender<WorkerMessage>, ctx: egui::Context) -> Self {
+        Self { sender, ctx }
+    }
+
+    pub fn send(&self, message: WorkerMessage) {
+        let _ = self.sender.send(message);
+        self.ctx.request_repaint();
+    }
+
+    pub fn log(&self, entry: LogEntry) {
+        self.send(WorkerMessage::Log(entry));
+    }
+}
+
+impl Drop for Reporter {
+    /// Wakes the GUI once more so it notices that the thread has finished.
+    fn drop(&mut self) {
+        self.ctx.request_repaint();
+    }
+}
+
+/// Formats a byte count for humans, e.g. "8.2 GB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Picks the destination for `file_name` inside `dir`. If a file with the same
+/// name already exists there, or is in `reserved`, a counter is added to avoid
+/// a collision.
+pub fn unique_destination(
+    dir: &Path,
+    file_path: &Path,
+    file_name: &OsStr,
+    reserved: &HashSet<PathBuf>,
+) -> PathBuf {
+    let taken = |path: &Path| path.exists() || reserved.contains(path);
+    let mut dest_path = dir.join(file_name);
+    let mut counter = 1;
+    while taken(&dest_path) {
+        let stem = file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("file");
+        let new_name = if let Some(extension) = file_path.extension().and_then(|s| s.to_str()) {
+            format!("{}_{}.{}", stem, counter, extension)
+        } else {
+            format!("{}_{}", stem, counter)
+        };
+        dest_path = dir.join(new_name);
+        counter += 1;
+    }
+    dest_path
+}
+
+/// Returns true if both files have the same content. Sizes are compared
+/// first so most files are told apart without hashing.
+pub fn is_duplicate(cache: &mut HashCache, a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    Ok(cache.hash(a)? == cache.hash(b)?)
+}
+
+/// Destination folders (the output folder, or each rule's folder) strictly
+/// inside `input_dir`, as paths relative to it. Scans skip them, as their
+/// files would be picked up again, e.g. when sorting Downloads into
+/// Downloads/Sorted. Only folders that already exist can hold files, so
+/// others are ignored.
+pub fn nested_destinations(
+    input_dir: &Path,
+    output_dir: &Path,
+    rules: Option<&[CompiledRule]>,
+) -> Vec<PathBuf> {
+    let destinations: Vec<PathBuf> = match rules {
+        Some(rules) => rules
+            .iter()
+            .map(|rule| output_dir.join(&rule.folder))
+            .collect(),
+        None => vec![output_dir.to_path_buf()],
+    };
+    let Ok(dir) = fs::canonicalize(input_dir) else {
+        return Vec::new();
+    };
+    let mut nested: Vec<PathBuf> = destinations
+        .iter()
+        .filter_map(|folder| {
+            let relative = fs::canonicalize(folder)
+                .ok()?
+                .strip_prefix(&dir)
+                .ok()?
+                .to_path_buf();
+            (relative.components().next().is_some()).then_some(relative)
+        })
+        .collect();
+    nested.sort();
+    nested.dedup();
+    nested
+}
+
+/// This function runs in a background thread. It recursively scans the input path
+/// and plans a move for all files with the specified extensions, sending the plan
+/// back via the provided reporter. Nothing is touched on disk.
+/// If the extensions string is empty, then every file is planned.
+/// Extensions in the same alias group as a listed one are planned too.
+/// When rules are given, the extensions string is ignored and each file goes
+/// into the folder of the first rule it matches; unmatched files stay put.
+pub fn scan_thread(settings: MoveSettings, reporter: Reporter) -> Result<(), Box<dyn Error>> {
+    let output_dir = PathBuf::from(&settings.output_path);
+
+    // Log into network shares first, so the paths can be reached below.
+    if let Some(credentials) = &settings.share_login {
+        let mut shares: Vec<String> = [&settings.input_path, &settings.output_path]
+            .into_iter()
+            .filter_map(|path| network::share_root(path))
+            .collect();
+        shares.dedup();
+        for share in shares {
+            if let Err(e) = network::connect(&share, credentials) {
+                reporter.log(LogEntry::error(format!(
+                    "Network share login failed: {}",
+                    e
+                )));
+                return Err(e.into());
+            }
+            reporter.log(LogEntry::info(format!("Connected to {}.", share)));
+        }
+    }
+
+    // Refuse to touch dangerous locations like the filesystem root or system folders.
+    for (label, path, contains_counts) in [
+        ("input", &settings.input_path, true),
+        ("output", &settings.output_path, false),
+    ] {
+        if let Some(entry) =
+            protected::blocking_entry(Path::new(path), &settings.protected_paths, contains_counts)
+        {
+            reporter.log(LogEntry::error(format!(
+                "Refusing to use {} as {}: it is protected by \"{}\". \
+                     Edit the protected paths list to allow it.",
+                path, label, entry
+            )));
+            return Err(format!("{} is a protected location.", path).into());
+        }
+    }
+
+    // If the user leaves the extensions field blank, filter_exts will be empty.
+    let alias_groups = filters::parse_aliases(&settings.aliases);
+    let filter_exts = filters::parse_extensions(&settings.extensions, &alias_groups);
+    let compiled_rules = settings
+        .rules
+        .as_deref()
+        .map(|rules| rules::compile(rules, &alias_groups));
+
+    // Tags are only looked up if a rule asks for them.
+    let tag_map = if compiled_rules.as_deref().is_some_and(rules::uses_tags) {
+        match TagStore::open().and_then(|store| Ok(store.all()?)) {
+            Ok(tag_map) => tag_map,
+            Err(e) => {
+                reporter.log(LogEntry::warning(format!(
+                    "Could not read tags, tag rules won't match: {}",
+                    e
+                )));
+                HashMap::new()
+            }
+        }
+    } else {
+        HashMap::new()
+    };
+    let file_tags = |file_path: &Path| -> &[String] {
+        if tag_map.is_empty() {
+            return &[];
+        }
+        tag_map
+            .get(&tags::key(file_path))
+            .map_or(&[], Vec::as_slice)
+    };
+
+    // Determine which folder (if any) a file should be moved into:
+    // - With rules, the folder of the first matching rule, along with the
+    //   rule's index.
+    // - Otherwise the output folder, if the extension filter allows it.
+    let target_dir = |file_path: &Path| -> Option<(PathBuf, Option<usize>)> {
+        match &compiled_rules {
+            Some(compiled) => rules::first_match(compiled, file_path, file_tags(file_path))
+                .map(|index| (output_dir.join(&compiled[index].folder), Some(index))),
+            None if filters::matches_extension(&filter_exts, file_path) => {
+                Some((output_dir.clone(), None))
+            }
+            None => None,
+        }
+    };
+
+    let owner_uid = match owner::parse_owner(&settings.owner) {
+        Ok(uid) => uid,
+        Err(e) => {
+            reporter.log(LogEntry::error(format!("Invalid owner filter: {}", e)));
+            return Err(e.into());
+        }
+    };
+    let renamer = Renamer::new(&settings.rename_template, &settings.rename_replacements);
+
+    let mut files: Vec<PathBuf> = if settings.input_type == InputType::Directory {
+        let input_dir = PathBuf::from(&settings.input_path);
+        if !input_dir.is_dir() {
+            reporter.log(LogEntry::error(format!(
+                "{} is not a valid directory.",
+                input_dir.display()
+            )));
+            return Err(format!("{} is not a valid directory.", input_dir.display()).into());
+        }
+        let nested = nested_destinations(&input_dir, &output_dir, compiled_rules.as_deref());
+        for relative in &nested {
+            reporter.log(LogEntry::info(format!(
+                "Skipping {}, a destination inside the input folder.",
+                input_dir.join(relative).display()
+            )));
+        }
+        let indexed = if settings.use_index {
+            let absolute = std::path::absolute(&input_dir).unwrap_or_else(|_| input_dir.clone());
+            match everything::list_files(&absolute) {
+                Ok(mut files) => {
+                    let skipped: Vec<PathBuf> = nested.iter().map(|r| absolute.join(r)).collect();
+                    files.retain(|file| !skipped.iter().any(|dir| file.starts_with(dir)));
+                    Some(files)
+                }
+                Err(e) => {
+                    reporter.log(LogEntry::warning(format!(
+                        "Everything index unavailable ({}), walking the folder instead.",
+                        e
+                    )));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        // Walk the directory recursively, unless the index already answered.
+        indexed.unwrap_or_else(|| {
+            let skipped: Vec<PathBuf> = nested.iter().map(|r| input_dir.join(r)).collect();
+            WalkDir::new(&input_dir)
+                .into_iter()
+                .filter_entry(|e| !skipped.iter().any(|dir| e.path() == dir))
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect()
+        })
+    } else {
+        // Input is a single file.
+        let file_path = PathBuf::from(&settings.input_path);
+        if !file_path.is_file() {
+            reporter.log(LogEntry::error(format!(
+                "{} is not a valid file.",
+                file_path.display()
+            )));
+            return Err(format!("{} is not a valid file.", file_path.display()).into());
+        }
+        vec![file_path]
+    };
+
+    if let Some(uid) = owner_uid {
+        files.retain(|file_path| owner::uid(file_path) == Some(uid));
+    }
+
+    let mut moves = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut hash_cache = settings.skip_duplicates.then(HashCache::load);
+    if let Some(cache) = hash_cache.as_mut() {
+        // Hash every possible duplicate up front in parallel, rather than one
+        // at a time in the loop below.
+        let candidates: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file_path| Some((target_dir(file_path)?.0, file_path)))
+            .enumerate()
+            .filter_map(|(index, (dir, file_path))| {
+                let existing = dir.join(renamer.apply(file_path, index + 1));
+                let same_size =
+                    fs::metadata(file_path).ok()?.len() == fs::metadata(&existing).ok()?.len();
+                same_size.then(|| [file_path.clone(), existing])
+            })
+            .flatten()
+            .collect();
+        cache.hash_all(&candidates);
+    }
+    // Destinations already handed out, so two files with the same name don't
+    // get planned onto the same path.
+    let mut reserved = HashSet::new();
+    // Position among the matching files, for the rename template's counter.
+    let mut matched = 0;
+    for file_path in files {
+        let Some((dir, rule_index)) = target_dir(&file_path) else {
+            continue;
+        };
+        matched += 1;
+        if file_path.file_name().is_none() {
+            reporter.log(LogEntry::warning(format!(
+                "Skipping file with invalid name: {}",
+                file_path.display()
+            )));
+            continue;
+        }
+        let new_name = renamer.apply(&file_path, matched);
+        let file_name = new_name.as_os_str();
+        if let Some(cache) = hash_cache.as_mut() {
+            let existing = dir.join(file_name);
+            if existing.is_file() {
+                match is_duplicate(cache, &file_path, &existing) {
+                    Ok(true) => {
+                        duplicates.push(file_path);
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => reporter.log(LogEntry::warning(format!(
+                        "Could not compare {} with {}: {}",
+                        file_path.display(),
+                        existing.display(),
+                        e
+                    ))),
+                }
+            }
+        }
+        let destination = unique_destination(&dir, Path::new(file_name), file_name, &reserved);
+        let collision = destination.file_name() != Some(file_name);
+        let metadata = fs::metadata(&file_path).ok();
+        reserved.insert(destination.clone());
+        moves.push(PlannedMove {
+            source: file_path,
+            destination,
+            size: metadata.as_ref().map_or(0, |m| m.len()),
+            collision,
+            rule: rule_index,
+            owner: metadata.as_ref().and_then(owner::uid_of),
+        });
+    }
+
+    // Tags of the planned files, for the preview. Looked up here rather than
+    // in the GUI, as resolving every path can take a while.
+    let tag_map = if tag_map.is_empty() {
+        TagStore::open()
+            .and_then(|store| Ok(store.all()?))
+            .unwrap_or_default()
+    } else {
+        tag_map
+    };
+    let tags = moves
+        .iter()
+        .map(|planned| {
+            if tag_map.is_empty() {
+                return String::new();
+            }
+            tag_map
+                .get(&tags::key(&planned.source))
+                .map(|tags| tags.join(", "))
+                .unwrap_or_default()
+        })
+        .collect();
+
+    if let Some(Err(e)) = hash_cache.as_mut().map(HashCache::save) {
+        reporter.log(LogEntry::warning(format!(
+            "Could not save the hash cache: {}",
+            e
+        )));
+    }
+
+    reporter.send(WorkerMessage::Plan(Plan {
+        input_path: settings.input_path,
+        output_path: settings.output_path,
+        moves,
+        duplicates,
+        copy: settings.copy,
+        rules: settings
+            .rules
+            .iter()
+            .flatten()
+            .map(|rule| rule.name.clone())
+            .collect(),
+        tags,
+    }));
+    Ok(())
+}
+
+/// What happened to a planned file.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Status {
+    Moved,
+    Copied,
+    Skipped,
+    Failed,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Moved => "moved",
+            Status::Copied => "copied",
+            Status::Skipped => "skipped",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+pub struct Outcome {
+    pub status: Status,
+    /// Where the file ended up, which may differ from the plan if something
+    /// appeared at the planned destination in the meantime.
+    pub destination: PathBuf,
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    fn failed(planned: &PlannedMove, error: String) -> Self {
+        Self {
+            status: Status::Failed,
+            destination: planned.destination.clone(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Moves a single planned file, reporting the outcome via the reporter.
+/// Each entry carries how long the file took. When resuming an interrupted
+/// job, `resumed` holds the run's hash cache, used to recognize files copied
+/// just before the interruption.
+pub fn move_file(
+    planned: &PlannedMove,
+    copy: bool,
+    resumed: Option<&mut HashCache>,
+    reporter: &Reporter,
+) -> Outcome {
+    let start = Instant::now();
+    let source = &planned.source;
+    if !source.exists() {
+        // A resumed job may retry files that were moved just before the crash.
+        let (entry, status) = if planned.destination.exists() {
+            let entry = LogEntry::info(format!(
+                "Already moved: {} -> {}",
+                source.display(),
+                planned.destination.display()
+            ));
+            (entry, Status::Moved)
+        } else {
+            let entry =
+                LogEntry::warning(format!("Skipped: {} no longer exists.", source.display()));
+            (entry, Status::Skipped)
+        };
+        reporter.log(entry);
+        return Outcome {
+            status,
+            destination: planned.destination.clone(),
+            error: None,
+        };
+    }
+    // Copies keep their source, so a resumed copy job recognizes files copied
+    // just before the crash by finding the same content at the destination.
+    let already_copied = match resumed {
+        Some(cache) if copy && planned.destination.is_file() => {
+            is_duplicate(cache, source, &planned.destination).unwrap_or(false)
+        }
+        _ => false,
+    };
+    if already_copied {
+        reporter.log(LogEntry::info(format!(
+            "Already copied: {} -> {}",
+            source.display(),
+            planned.destination.display()
+        )));
+        return Outcome {
+            status: Status::Copied,
+            destination: planned.destination.clone(),
+            error: None,
+        };
+    }
+    let Some(target_dir) = planned.destination.parent() else {
+        return Outcome::failed(planned, "invalid destination".to_string());
+    };
+    if let Err(e) = fs::create_dir_all(target_dir) {
+        reporter.log(
+            LogEntry::error(format!("Error creating {}: {}", target_dir.display(), e))
+                .with_duration(start.elapsed()),
+        );
+        return Outcome::failed(planned, e.to_string());
+    }
+    // Something may have appeared at the destination since the scan.
+    let dest_path = if planned.destination.exists() {
+        let file_name = source.file_name().unwrap_or_default();
+        unique_destination(target_dir, source, file_name, &HashSet::new())
+    } else {
+        planned.destination.clone()
+    };
+    // Attempt to move (rename) or copy the file. Renaming doesn't work across
+    // volumes, so such moves fall back to copying and removing the original.
+    let (result, status, verb) = if copy {
+        (
+            copy::copy_file(source, &dest_path),
+            Status::Copied,
+            "copying",
+        )
+    } else {
+        let result = match fs::rename(source, &dest_path) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                copy::move_across_devices(source, &dest_path)
+            }
+            result => result.map(|()| Vec::new()),
+        };
+        (result, Status::Moved, "moving")
+    };
+    match result {
+        Ok(lost) => {
+            if !lost.is_empty() {
+                reporter.log(LogEntry::warning(format!(
+                    "Could not carry over to {}: {}",
+                    dest_path.display(),
+                    lost.join(", ")
+                )));
+            }
+            let action = if copy { "Copied" } else { "Moved" };
+            reporter.log(
+                LogEntry::info(format!(
+                    "{}: {} -> {}",
+                    action,
+                    source.display(),
+                    dest_path.display()
+                ))
+                .with_duration(start.elapsed()),
+            );
+            Outcome {
+                status,
+                destination: dest_path,
+                error: None,
+            }
+        }
+        Err(e) => {
+            reporter.log(
+                LogEntry::error(format!("Error {} {}: {}", verb, source.display(), e))
+                    .with_duration(start.elapsed()),
+            );
+            Outcome::failed(planned, e.to_string())
+        }
+    }
+}
+
+/// How often the number of processed files is saved for crash recovery.
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// This function runs in a background thread and carries out a confirmed plan,
+/// sending progress messages back via the provided reporter.
+/// `resume_from` resumes an interrupted job, skipping the entries it had
+/// completed. While running, the job is persisted so a crash can be resumed
+/// on restart. If the plan was previewed, `dry_run` reports how the run
+/// diverged from it.
+pub fn execute_thread(
+    plan: Plan,
+    resume_from: Option<usize>,
+    mut dry_run: Option<DryRunCheck>,
+    reporter: Reporter,
+) {
+    let saved = match resume_from {
+        None => job::save_plan(&plan),
+        Some(completed) => job::save_progress(completed),
+    };
+    if let Err(e) = saved {
+        reporter.log(LogEntry::warning(format!(
+            "Could not save job state, an interrupted run can't be resumed: {}",
+            e
+        )));
+    }
+
+    // Every operation is recorded in the history database, if it can be opened.
+    let history = History::open().and_then(|history| {
+        let run_id = history.start_run(&plan)?;
+        history.begin()?;
+        Ok((history, run_id))
+    });
+    let history = match history {
+        Ok(history) => Some(history),
+        Err(e) => {
+            reporter.log(LogEntry::warning(format!(
+                "Could not open the history database, this run won't be recorded: {}",
+                e
+            )));
+            None
+        }
+    };
+
+    // Tags follow their files to the new location.
+    let tag_store = TagStore::open().ok();
+    let tagged: HashSet<String> = tag_store
+        .as_ref()
+        .and_then(|store| store.all().ok())
+        .map(|all| all.into_keys().collect())
+        .unwrap_or_default();
+
+    // Applied whenever the history commits, as the history's open transaction
+    // keeps the shared database locked in between.
+    let mut tag_moves: Vec<(String, PathBuf)> = Vec::new();
+    let apply_tag_moves = |tag_moves: &mut Vec<(String, PathBuf)>| {
+        if let Some(store) = &tag_store {
+            for (key, destination) in tag_moves.drain(..) {
+                let _ = store.move_path(&key, &destination);
+            }
+        }
+    };
+
+    // A full or locked database would leave gaps in the history, which is
+    // what undoing moves relies on; warned about once per run.
+    let mut history_failed = false;
+    let mut warn_history = |result: rusqlite::Result<()>| {
+        if let Err(e) = result {
+            if !history_failed {
+                history_failed = true;
+                reporter.log(LogEntry::warning(format!(
+                    "Could not record to the history database, this run's history \
+                     is incomplete: {}",
+                    e
+                )));
+            }
+        }
+    };
+    let mut rule_stats = RuleStats::new(&plan);
+    let mut last_save = Instant::now();
+    // Files done after the last progress save are recognized when resuming.
+    let mut resume_cache = resume_from.map(|_| HashCache::default());
+    for (index, planned) in plan.moves.iter().enumerate().skip(resume_from.unwrap_or(0)) {
+        let start = Instant::now();
+        let source_key = (!tagged.is_empty()).then(|| tags::key(&planned.source));
+        let outcome = move_file(planned, plan.copy, resume_cache.as_mut(), &reporter);
+        if let Some((history, run_id)) = &history {
+            warn_history(history.record(*run_id, planned, &outcome, start.elapsed()));
+        }
+        if let Some(check) = dry_run.as_mut() {
+            check.record(planned, &outcome);
+        }
+        if let Some(stats) = rule_stats.as_mut() {
+            stats.record(planned, &outcome);
+        }
+        if let Some(key) = source_key {
+            if outcome.status == Status::Moved && tagged.contains(&key) {
+                tag_moves.push((key, outcome.destination));
+            }
+        }
+        reporter.send(WorkerMessage::Progress {
+            done: index + 1,
+            total: plan.moves.len(),
+        });
+        if last_save.elapsed() >= PROGRESS_SAVE_INTERVAL {
+            let _ = job::save_progress(index + 1);
+            if let Some((history, _)) = &history {
+                warn_history(history.checkpoint());
+            }
+            apply_tag_moves(&mut tag_moves);
+            last_save = Instant::now();
+        }
+    }
+    if let Some((history, run_id)) = &history {
+        warn_history(history.commit());
+        warn_history(history.finish_run(*run_id));
+    }
+    apply_tag_moves(&mut tag_moves);
+    job::clear();
+    if let Some(check) = &dry_run {
+        check.report(&reporter);
+    }
+    if let Some(stats) = &rule_stats {
+        stats.report(plan.copy, &reporter);
+    }
+    reporter.log(LogEntry::info(if plan.copy {
+        "Copying completed successfully."
+    } else {
+        "Moving completed successfully."
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh empty folder, unique to the test.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("move_files_gui_mover_{}", test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn reporter() -> Reporter {
+        let (tx, _rx) = mpsc::channel();
+        Reporter::new(tx, egui::Context::default())
+    }
+
+    fn planned(source: PathBuf, destination: PathBuf) -> PlannedMove {
+        PlannedMove {
+            size: fs::metadata(&source).map_or(0, |m| m.len()),
+            source,
+            destination,
+            collision: false,
+            rule: None,
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn unique_destination_numbers_taken_names() {
+        let dir = temp_dir("unique");
+        let name = OsStr::new("a.txt");
+        let mut reserved = HashSet::new();
+        let first = unique_destination(&dir, Path::new(name), name, &reserved);
+        assert_eq!(first, dir.join("a.txt"));
+
+        fs::write(&first, "a").unwrap();
+        let second = unique_destination(&dir, Path::new(name), name, &reserved);
+        assert_eq!(second, dir.join("a_1.txt"));
+
+        reserved.insert(second);
+        let third = unique_destination(&dir, Path::new(name), name, &reserved);
+        assert_eq!(third, dir.join("a_2.txt"));
+
+        fs::write(dir.join("README"), "").unwrap();
+        let name = OsStr::new("README");
+        let numbered = unique_destination(&dir, Path::new(name), name, &reserved);
+        assert_eq!(numbered, dir.join("README_1"));
+    }
+
+    #[test]
+    fn finds_destinations_inside_the_input() {
+        let dir = temp_dir("nested");
+        fs::create_dir_all(dir.join("Sorted").join("Images")).unwrap();
+        fs::create_dir_all(dir.join("Elsewhere")).unwrap();
+        assert_eq!(
+            nested_destinations(&dir, &dir.join("Sorted"), None),
+            [PathBuf::from("Sorted")]
+        );
+        assert!(nested_destinations(&dir, &dir, None).is_empty());
+        assert!(nested_destinations(&dir.join("Sorted"), &dir.join("Elsewhere"), None).is_empty());
+
+        // Rule folders that don't exist yet can't hold files.
+        let rules = rules::compile(
+            &[
+                Rule::new("Images", "jpg", "Images"),
+                Rule::new("Music", "mp3", "Music"),
+                Rule::new("Images too", "png", "Images"),
+            ],
+            &[],
+        );
+        assert_eq!(
+            nested_destinations(&dir, &dir.join("Sorted"), Some(&rules)),
+            [Path::new("Sorted").join("Images")]
+        );
+    }
+
+    #[test]
+    fn resumed_copy_skips_files_already_copied() {
+        let dir = temp_dir("resumed_copy");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let planned = planned(dir.join("a.txt"), dir.join("out").join("a.txt"));
+        move_file(&planned, true, None, &reporter());
+
+        let mut cache = HashCache::default();
+        let outcome = move_file(&planned, true, Some(&mut cache), &reporter());
+        assert!(outcome.status == Status::Copied);
+        assert_eq!(outcome.destination, planned.destination);
+        assert!(!dir.join("out").join("a_1.txt").exists());
+    }
+
+    #[test]
+    fn fresh_copy_keeps_both_when_destination_appeared() {
+        let dir = temp_dir("fresh_copy");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let planned = planned(dir.join("a.txt"), dir.join("out").join("a.txt"));
+        move_file(&planned, true, None, &reporter());
+
+        let outcome = move_file(&planned, true, None, &reporter());
+        assert!(outcome.status == Status::Copied);
+        assert_eq!(outcome.destination, dir.join("out").join("a_1.txt"));
+        assert!(outcome.destination.exists());
+    }
+}
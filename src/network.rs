@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+/// Login for a network share. An empty username uses the credentials Windows
+/// already has for the share, e.g. saved in the Credential Manager.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    /// Never written to disk, e.g. in profiles.
+    #[serde(skip)]
+    pub password: String,
+}
+
+/// The `\\server\share` part of a UNC path, if `path` is one.
+pub fn share_root(path: &str) -> Option<String> {
+    let rest = path
+        .strip_prefix(r"\\")
+        .or_else(|| path.strip_prefix("//"))?;
+    // Device paths like \\?\C:\ or \\.\pipe aren't shares.
+    if rest.starts_with(['?', '.']) {
+        return None;
+    }
+    let mut parts = rest.split(['\\', '/']).filter(|part| !part.is_empty());
+    let server = parts.next()?;
+    let share = parts.next()?;
+    Some(format!(r"\\{}\{}", server, share))
+}
+
+/// Connects to a network share so its files can be used even if it wasn't
+/// mounted or logged into before. The connection isn't remembered after
+/// logging off.
+#[cfg(windows)]
+pub fn connect(share: &str, credentials: &Credentials) -> Result<(), String> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+
+    use winapi::shared::winerror::{
+        ERROR_ACCESS_DENIED, ERROR_ACCOUNT_DISABLED, ERROR_BAD_NETPATH, ERROR_BAD_NET_NAME,
+        ERROR_BAD_USERNAME, ERROR_INVALID_PASSWORD, ERROR_LOGON_FAILURE, ERROR_NO_NETWORK,
+        ERROR_PASSWORD_EXPIRED, ERROR_SESSION_CREDENTIAL_CONFLICT, NO_ERROR,
+    };
+    use winapi::um::winnetwk::{
+        WNetAddConnection2W, CONNECT_TEMPORARY, NETRESOURCEW, RESOURCETYPE_DISK,
+    };
+
+    let wide = |text: &str| -> Vec<u16> { OsStr::new(text).encode_wide().chain(Some(0)).collect() };
+    let mut remote_name = wide(share);
+    // SAFETY: NETRESOURCEW is a plain C struct for which all zeroes is valid.
+    let mut resource: NETRESOURCEW = unsafe { std::mem::zeroed() };
+    resource.dwType = RESOURCETYPE_DISK;
+    resource.lpRemoteName = remote_name.as_mut_ptr();
+    let login = (!credentials.username.trim().is_empty()).then(|| {
+        (
+            wide(credentials.username.trim()),
+            wide(&credentials.password),
+        )
+    });
+    let (username, password) = match &login {
+        Some((username, password)) => (username.as_ptr(), password.as_ptr()),
+        // Null lets Windows use the current user's or stored credentials.
+        None => (ptr::null(), ptr::null()),
+    };
+    // SAFETY: every pointer refers to a NUL-terminated buffer alive for the call.
+    let result =
+        unsafe { WNetAddConnection2W(&mut resource, password, username, CONNECT_TEMPORARY) };
+    let reason = match result {
+        NO_ERROR => return Ok(()),
+        ERROR_LOGON_FAILURE | ERROR_INVALID_PASSWORD | ERROR_BAD_USERNAME => {
+            "the username or password is wrong"
+        }
+        ERROR_ACCESS_DENIED => "access was denied for this user",
+        ERROR_ACCOUNT_DISABLED => "the account is disabled",
+        ERROR_PASSWORD_EXPIRED => "the password has expired",
+        ERROR_SESSION_CREDENTIAL_CONFLICT => {
+            "this server is already connected under another user; disconnect it first \
+             (e.g. \"net use * /delete\")"
+        }
+        ERROR_BAD_NET_NAME => "the share doesn't exist on the server",
+        ERROR_BAD_NETPATH => "the server can't be reached",
+        ERROR_NO_NETWORK => "the network is unavailable",
+        _ => return Err(format!("could not connect to {} (error {})", share, result)),
+    };
+    Err(format!("could not connect to {}: {}", share, reason))
+}
+
+#[cfg(not(windows))]
+pub fn connect(share: &str, _credentials: &Credentials) -> Result<(), String> {
+    Err(format!(
+        "could not connect to {}: logging into shares is only supported on Windows, \
+         mount the share instead",
+        share
+    ))
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+/// Resolves the owner filter, a user name or numeric UID, to a UID. Empty
+/// text means no filter.
+#[cfg(unix)]
+pub fn parse_owner(text: &str) -> Result<Option<u32>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if let Some(user) = uzers::get_user_by_name(text) {
+        return Ok(Some(user.uid()));
+    }
+    text.parse()
+        .map(Some)
+        .map_err(|_| format!("there is no user named \"{}\"", text))
+}
+
+#[cfg(not(unix))]
+pub fn parse_owner(text: &str) -> Result<Option<u32>, String> {
+    if text.trim().is_empty() {
+        Ok(None)
+    } else {
+        Err("filtering by owner is only supported on Unix".to_string())
+    }
+}
+
+/// UID of the file's owner.
+pub fn uid(path: &Path) -> Option<u32> {
+    uid_of(&std::fs::metadata(path).ok()?)
+}
+
+/// UID of the owner of the file the metadata was read from.
+#[cfg(unix)]
+pub fn uid_of(metadata: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+pub fn uid_of(_metadata: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Looks up the names of file owners, remembering users already seen.
+#[derive(Default)]
+pub struct OwnerNames {
+    #[cfg(unix)]
+    users: uzers::UsersCache,
+}
+
+impl OwnerNames {
+    /// The name of the user with this UID, or the UID if the user is unknown.
+    #[cfg(unix)]
+    pub fn name(&self, uid: u32) -> String {
+        use uzers::Users;
+
+        match self.users.get_user_by_uid(uid) {
+            Some(user) => user.name().to_string_lossy().into_owned(),
+            None => uid.to_string(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn name(&self, uid: u32) -> String {
+        uid.to_string()
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::mover::MoveSettings;
+use crate::protected;
+use crate::storage;
+
+/// Saved settings of the Move tab, one JSON file per profile, so jobs can
+/// be run again by name, also from the command line (`--profile <name>`).
+const PROFILES_DIR: &str = "profiles";
+
+fn profile_path(name: &str) -> io::Result<PathBuf> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', ':']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("\"{}\" is not a valid profile name", name),
+        ));
+    }
+    let dir = storage::data_dir()?.join(PROFILES_DIR);
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// Saves the settings under `name`, replacing a profile of that name.
+/// Share passwords are left out.
+pub fn save(name: &str, settings: &MoveSettings) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(settings)?;
+    storage::write_atomic(&profile_path(name)?, &json)
+}
+
+/// Loads a profile. Profiles don't carry protected paths, so the default
+/// list is filled in.
+pub fn load(name: &str) -> io::Result<MoveSettings> {
+    let json = fs::read(profile_path(name)?).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            e.kind(),
+            format!("there is no profile named \"{}\"", name.trim()),
+        ),
+        _ => e,
+    })?;
+    let settings: MoveSettings = serde_json::from_slice(&json)?;
+    Ok(MoveSettings {
+        protected_paths: protected::default_list(),
+        ..settings
+    })
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(profile_path(name)?)
+}
+
+/// Names of the saved profiles, sorted.
+pub fn names() -> Vec<String> {
+    let Ok(dir) = storage::data_dir().map(|dir| dir.join(PROFILES_DIR)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The protected paths shipped by default, one per line.
+/// `~` stands for the user's home folder, and an entry ending in `*` also
+/// protects everything beneath it.
+pub fn default_list() -> String {
+    let entries: Vec<String> = if cfg!(windows) {
+        let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        vec![
+            format!("{}\\", drive),
+            "~".to_string(),
+            format!("{}\\Windows\\*", drive),
+            format!("{}\\Program Files\\*", drive),
+            format!("{}\\Program Files (x86)\\*", drive),
+            format!("{}\\ProgramData\\*", drive),
+        ]
+    } else {
+        let mut entries = vec!["/", "~"];
+        entries.extend([
+            "/bin/*", "/boot/*", "/dev/*", "/etc/*", "/lib/*", "/proc/*", "/sbin/*", "/sys/*",
+            "/usr/*", "/var/*",
+        ]);
+        if cfg!(target_os = "macos") {
+            entries.extend(["/System/*", "/Library/*", "/Applications/*"]);
+        }
+        entries.into_iter().map(String::from).collect()
+    };
+    entries.join("\n")
+}
+
+/// Resolves symlinks and `..` so that e.g. `/home/me/..` is seen as `/home`.
+/// Paths that don't exist yet are resolved through their nearest existing parent.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return resolved;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => resolve(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Makes paths comparable; Windows paths are case-insensitive.
+fn normalize(path: &Path) -> PathBuf {
+    let resolved = resolve(path);
+    if cfg!(windows) {
+        PathBuf::from(resolved.to_string_lossy().to_lowercase())
+    } else {
+        resolved
+    }
+}
+
+/// Checks `path` against the protected list and returns the entry that
+/// blocks it, if any. A path is blocked when it is a listed location, lies
+/// beneath an entry ending in `*`, or (with `contains_counts`) contains a
+/// listed location, as moving out of `/home` would also empty the profile.
+pub fn blocking_entry(path: &Path, list: &str, contains_counts: bool) -> Option<String> {
+    let path = normalize(path);
+    list.lines()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .find(|entry| {
+            let (raw, recursive) = match entry.strip_suffix('*') {
+                Some(raw) => (raw, true),
+                None => (*entry, false),
+            };
+            let protected = match raw.strip_prefix('~') {
+                Some(rest) => match dirs::home_dir() {
+                    Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+                    None => return false,
+                },
+                None => PathBuf::from(raw),
+            };
+            let protected = normalize(&protected);
+            path == protected
+                || (recursive && path.starts_with(&protected))
+                || (contains_counts && protected.starts_with(&path))
+        })
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh folder with an `a/b` sub-folder, unique to the test.
+    fn temp_tree(test: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("move_files_gui_protected_{}", test));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+        root
+    }
+
+    fn entry(path: &Path) -> String {
+        path.display().to_string()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn filesystem_root_is_protected_by_default() {
+        assert_eq!(
+            blocking_entry(Path::new("/"), &default_list(), true),
+            Some("/".to_string())
+        );
+        assert!(blocking_entry(Path::new("/usr/share"), &default_list(), false).is_some());
+    }
+
+    #[test]
+    fn blocks_a_listed_location() {
+        let root = temp_tree("listed");
+        let list = entry(&root.join("a"));
+        assert_eq!(blocking_entry(&root.join("a"), &list, false), Some(list));
+    }
+
+    #[test]
+    fn blocks_paths_nested_under_a_recursive_entry() {
+        let root = temp_tree("recursive");
+        let list = format!("{}*", entry(&root.join("a")));
+        assert!(blocking_entry(&root.join("a").join("b"), &list, false).is_some());
+        // Paths that don't exist yet are resolved through their parent.
+        assert!(blocking_entry(&root.join("a").join("new"), &list, false).is_some());
+    }
+
+    #[test]
+    fn allows_paths_nested_under_a_plain_entry() {
+        let root = temp_tree("plain");
+        let list = entry(&root.join("a"));
+        assert_eq!(
+            blocking_entry(&root.join("a").join("b"), &list, false),
+            None
+        );
+    }
+
+    #[test]
+    fn contains_counts_blocks_parents_of_an_entry() {
+        let root = temp_tree("contains");
+        let list = entry(&root.join("a").join("b"));
+        assert!(blocking_entry(&root, &list, true).is_some());
+        assert_eq!(blocking_entry(&root, &list, false), None);
+    }
+
+    #[test]
+    fn ignores_trailing_separators_and_dot_dot() {
+        let root = temp_tree("separators");
+        let list = format!("{}{}", entry(&root.join("a")), std::path::MAIN_SEPARATOR);
+        assert!(blocking_entry(&root.join("a"), &list, false).is_some());
+        let with_dot_dot = root.join("a").join("b").join("..");
+        assert!(blocking_entry(&with_dot_dot, &entry(&root.join("a")), false).is_some());
+    }
+
+    #[test]
+    fn case_only_matters_off_windows() {
+        let root = temp_tree("case");
+        let list = entry(&root.join("a")).to_uppercase();
+        assert_eq!(
+            blocking_entry(&root.join("a"), &list, false).is_some(),
+            cfg!(windows)
+        );
+    }
+
+    #[test]
+    fn allows_unlisted_paths() {
+        let root = temp_tree("allowed");
+        let list = format!(
+            "{}\n\n  {}*  ",
+            entry(&root.join("x")),
+            entry(&root.join("y"))
+        );
+        assert_eq!(blocking_entry(&root.join("a"), &list, true), None);
+        assert_eq!(blocking_entry(&root.join("a"), "", true), None);
+    }
+}
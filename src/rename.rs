@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use walkdir::WalkDir;
+
+use crate::filters;
+use crate::logging::LogEntry;
+use crate::mover::{self, PlannedMove, Reporter, Status, WorkerMessage};
+use crate::protected;
+use crate::tags::{self, TagStore};
+
+/// Shown next to the template field.
+pub const TEMPLATE_HELP: &str = "{name} original name, {ext} extension, {n} counter \
+    ({n:3} pads it to 3 digits, at most 10), {date} modification date";
+
+/// Widest padding `{n:N}` gives the counter; wider requests are cut down to
+/// it, as huge widths would build huge names.
+const MAX_PAD_WIDTH: usize = 10;
+
+/// Parses character replacements written as comma-separated "from=to" pairs,
+/// e.g. " =_, #=". An empty "to" removes the characters.
+pub fn parse_replacements(text: &str) -> Vec<(String, String)> {
+    text.split(',')
+        .filter_map(|pair| {
+            let (from, to) = pair.split_once('=')?;
+            // A single space after the comma is just formatting, yet " =_"
+            // alone still replaces spaces.
+            let from = from
+                .strip_prefix(' ')
+                .filter(|f| !f.is_empty())
+                .unwrap_or(from);
+            (!from.is_empty()).then(|| (from.to_string(), to.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Builds new file names from a template and character replacements. An
+/// empty template keeps the original name.
+#[derive(Clone, Default)]
+pub struct Renamer {
+    template: String,
+    replacements: Vec<(String, String)>,
+}
+
+impl Renamer {
+    pub fn new(template: &str, replacements: &str) -> Self {
+        Self {
+            template: template.trim().to_string(),
+            replacements: parse_replacements(replacements),
+        }
+    }
+
+    /// True if every name would stay as it is.
+    pub fn is_identity(&self) -> bool {
+        self.template.is_empty() && self.replacements.is_empty()
+    }
+
+    /// The new file name for `path`. `n` is the file's position in the
+    /// batch, starting at 1.
+    pub fn apply(&self, path: &Path, n: usize) -> OsString {
+        let original = path.file_name().unwrap_or_default();
+        if self.is_identity() {
+            return original.to_os_string();
+        }
+        let mut name = if self.template.is_empty() {
+            original.to_string_lossy().into_owned()
+        } else {
+            self.render(path, n)
+        };
+        for (from, to) in &self.replacements {
+            name = name.replace(from.as_str(), to);
+        }
+        // The result must stay a plain file name.
+        let name = name.replace(['/', '\\'], "_");
+        let name = name.trim().trim_end_matches('.');
+        if name.is_empty() {
+            original.to_os_string()
+        } else {
+            OsString::from(name)
+        }
+    }
+
+    fn render(&self, path: &Path, n: usize) -> String {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = path.extension().map(|e| e.to_string_lossy());
+        let mut rendered = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find('{') {
+            // An unclosed brace is kept as typed, with the rest.
+            let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+                break;
+            };
+            rendered.push_str(&rest[..open]);
+            let placeholder = &rest[open + 1..close];
+            match placeholder {
+                "name" => rendered.push_str(&stem),
+                "ext" => rendered.push_str(extension.as_deref().unwrap_or_default()),
+                "n" => rendered.push_str(&n.to_string()),
+                "date" => rendered.push_str(&modified_date(path)),
+                _ => match placeholder.strip_prefix("n:").and_then(|w| w.parse().ok()) {
+                    Some(width) => rendered.push_str(&format!(
+                        "{:0width$}",
+                        n,
+                        width = usize::min(width, MAX_PAD_WIDTH)
+                    )),
+                    // Unknown placeholders are kept as typed.
+                    None => rendered.push_str(&rest[open..=close]),
+                },
+            }
+            rest = &rest[close + 1..];
+        }
+        rendered.push_str(rest);
+        // "{name}.{ext}" on a file without an extension shouldn't leave a dot.
+        if extension.is_none() {
+            if let Some(trimmed) = rendered.strip_suffix('.') {
+                rendered.truncate(trimmed.len());
+            }
+        }
+        rendered
+    }
+}
+
+fn modified_date(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|time| DateTime::<Local>::from(time).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// This function runs in a background thread. It plans renaming the files in
+/// `folder` (and its sub-folders if `recursive`) that match `extensions`,
+/// and sends the planned renames back via the provided reporter. Files keep
+/// their folder; nothing is touched on disk.
+pub fn scan_thread(
+    folder: PathBuf,
+    recursive: bool,
+    extensions: Vec<String>,
+    renamer: Renamer,
+    protected_paths: String,
+    reporter: Reporter,
+) {
+    if !folder.is_dir() {
+        reporter.log(LogEntry::error(format!(
+            "{} is not a valid directory.",
+            folder.display()
+        )));
+        return;
+    }
+    // Renaming system files breaks whatever looks for them by name.
+    if let Some(entry) = protected::blocking_entry(&folder, &protected_paths, true) {
+        reporter.log(LogEntry::error(format!(
+            "Refusing to rename files in {}: it is protected by \"{}\". \
+                 Edit the protected paths list to allow it.",
+            folder.display(),
+            entry
+        )));
+        return;
+    }
+    let mut walker = WalkDir::new(&folder).sort_by_file_name();
+    if !recursive {
+        walker = walker.max_depth(1);
+    }
+    let files = walker
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| filters::matches_extension(&extensions, path));
+
+    let mut renames = Vec::new();
+    let mut reserved = HashSet::new();
+    for (index, file_path) in files.enumerate() {
+        let new_name = renamer.apply(&file_path, index + 1);
+        if Some(new_name.as_os_str()) == file_path.file_name() {
+            continue;
+        }
+        let dir = file_path.parent().unwrap_or(&folder);
+        let destination =
+            mover::unique_destination(dir, Path::new(&new_name), &new_name, &reserved);
+        let collision = destination.file_name() != Some(new_name.as_os_str());
+        let size = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+        reserved.insert(destination.clone());
+        renames.push(PlannedMove {
+            source: file_path,
+            destination,
+            size,
+            collision,
+            rule: None,
+            owner: None,
+        });
+    }
+    reporter.log(LogEntry::info(format!(
+        "{} files would be renamed.",
+        renames.len()
+    )));
+    reporter.send(WorkerMessage::Renames(renames));
+}
+
+/// This function runs in a background thread. It renames the files and
+/// reports what was actually renamed, so that it can be undone. With `undo`,
+/// the renames are ones from `undo_plan` and nothing is reported back.
+pub fn rename_thread(renames: Vec<PlannedMove>, undo: bool, reporter: Reporter) {
+    let tag_store = TagStore::open().ok();
+    let mut done = Vec::new();
+    for (index, planned) in renames.iter().enumerate() {
+        let source_key = tags::key(&planned.source);
+        let outcome = mover::move_file(planned, false, None, &reporter);
+        if outcome.status == Status::Moved {
+            if let Some(store) = &tag_store {
+                let _ = store.move_path(&source_key, &outcome.destination);
+            }
+            done.push(PlannedMove {
+                source: planned.source.clone(),
+                destination: outcome.destination,
+                size: planned.size,
+                collision: planned.collision,
+                rule: None,
+                owner: planned.owner,
+            });
+        }
+        reporter.send(WorkerMessage::Progress {
+            done: index + 1,
+            total: renames.len(),
+        });
+    }
+    if undo {
+        reporter.log(LogEntry::info(format!(
+            "Restored the names of {} files.",
+            done.len()
+        )));
+    } else {
+        reporter.log(LogEntry::info(format!("Renamed {} files.", done.len())));
+        reporter.send(WorkerMessage::Renamed(done));
+    }
+}
+
+/// The renames that put the files back where `done` took them from.
+pub fn undo_plan(done: &[PlannedMove]) -> Vec<PlannedMove> {
+    done.iter()
+        .rev()
+        .map(|planned| PlannedMove {
+            source: planned.destination.clone(),
+            destination: planned.source.clone(),
+            size: planned.size,
+            collision: false,
+            rule: None,
+            owner: planned.owner,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(template: &str, replacements: &str, path: &str, n: usize) -> String {
+        Renamer::new(template, replacements)
+            .apply(Path::new(path), n)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn replacements_are_parsed_from_pairs() {
+        assert_eq!(
+            parse_replacements(" =_, #=, bad,=x"),
+            vec![
+                (" ".to_string(), "_".to_string()),
+                ("#".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn template_placeholders_are_filled_in() {
+        assert_eq!(
+            rename("{name}-{n}.{ext}", "", "dir/photo.jpg", 7),
+            "photo-7.jpg"
+        );
+        assert_eq!(rename("img_{n:3}.{ext}", "", "a.png", 12), "img_012.png");
+        assert_eq!(rename("{name}.{ext}", "", "README", 1), "README");
+    }
+
+    #[test]
+    fn counter_padding_is_limited() {
+        assert_eq!(rename("{n:999999999}", "", "a", 5), "0000000005");
+    }
+
+    #[test]
+    fn unknown_placeholders_and_unclosed_braces_are_kept() {
+        assert_eq!(rename("{what}_{name}", "", "a.txt", 1), "{what}_a");
+        assert_eq!(rename("photo {n", "", "a.txt", 1), "photo {n");
+    }
+
+    #[test]
+    fn names_stay_plain_file_names() {
+        assert_eq!(rename("", " =_", "my file.txt", 1), "my_file.txt");
+        assert_eq!(rename("a/b\\{name}", "", "c.txt", 1), "a_b_c");
+        // A name that would end up empty keeps the original.
+        assert_eq!(rename("", "x=", "x", 1), "x");
+        assert_eq!(rename("", "", "same.txt", 1), "same.txt");
+    }
+}
@@ -0,0 +1,130 @@
+use crate::logging::LogEntry;
+use crate::mover::{self, Outcome, Plan, PlannedMove, Reporter, Status};
+
+#[derive(Default)]
+struct Stats {
+    files: usize,
+    bytes: u64,
+    errors: usize,
+}
+
+/// Totals per rule for a run sorted by rules, to see which rules do the work
+/// and which never match anything.
+pub struct RuleStats {
+    /// Indexed like `Plan::rules`; rules that matched nothing keep zeroes.
+    rules: Vec<(String, Stats)>,
+}
+
+impl RuleStats {
+    /// None unless the plan was made by rules.
+    pub fn new(plan: &Plan) -> Option<Self> {
+        if plan.rules.is_empty() {
+            return None;
+        }
+        let rules = plan
+            .rules
+            .iter()
+            .map(|name| (name.clone(), Stats::default()))
+            .collect();
+        Some(Self { rules })
+    }
+
+    pub fn record(&mut self, planned: &PlannedMove, outcome: &Outcome) {
+        let Some((_, stats)) = planned.rule.and_then(|index| self.rules.get_mut(index)) else {
+            return;
+        };
+        stats.files += 1;
+        match outcome.status {
+            Status::Moved | Status::Copied => stats.bytes += planned.size,
+            Status::Failed => stats.errors += 1,
+            Status::Skipped => {}
+        }
+    }
+
+    /// Logs a line per rule.
+    pub fn report(&self, copy: bool, reporter: &Reporter) {
+        let verb = if copy { "copied" } else { "moved" };
+        // Numbered like the rules table, as names can repeat.
+        for (index, (name, stats)) in self.rules.iter().enumerate() {
+            let text = if stats.files == 0 {
+                format!("Rule {} \"{}\": matched nothing", index + 1, name)
+            } else {
+                format!(
+                    "Rule {} \"{}\": {} files, {} {}, {} errors",
+                    index + 1,
+                    name,
+                    stats.files,
+                    mover::format_size(stats.bytes),
+                    verb,
+                    stats.errors
+                )
+            };
+            reporter.log(if stats.errors > 0 {
+                LogEntry::warning(text)
+            } else {
+                LogEntry::info(text)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn planned(rule: Option<usize>, size: u64) -> PlannedMove {
+        PlannedMove {
+            source: PathBuf::from("in/a"),
+            destination: PathBuf::from("out/a"),
+            size,
+            collision: false,
+            rule,
+            owner: None,
+        }
+    }
+
+    fn outcome(status: Status) -> Outcome {
+        Outcome {
+            status,
+            destination: PathBuf::from("out/a"),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn only_for_plans_made_by_rules() {
+        let mut plan = Plan {
+            input_path: "in".into(),
+            output_path: "out".into(),
+            moves: Vec::new(),
+            duplicates: Vec::new(),
+            copy: false,
+            rules: Vec::new(),
+            tags: Vec::new(),
+        };
+        assert!(RuleStats::new(&plan).is_none());
+        plan.rules = vec!["Images".into(), "Music".into()];
+        assert!(RuleStats::new(&plan).is_some_and(|stats| stats.rules.len() == 2));
+    }
+
+    #[test]
+    fn totals_each_rule() {
+        let mut stats = RuleStats {
+            rules: vec![
+                ("Images".into(), Stats::default()),
+                ("Music".into(), Stats::default()),
+            ],
+        };
+        stats.record(&planned(Some(0), 10), &outcome(Status::Moved));
+        stats.record(&planned(Some(0), 20), &outcome(Status::Failed));
+        stats.record(&planned(Some(0), 40), &outcome(Status::Skipped));
+        stats.record(&planned(None, 80), &outcome(Status::Moved));
+        stats.record(&planned(Some(5), 80), &outcome(Status::Moved));
+
+        let (_, images) = &stats.rules[0];
+        assert_eq!((images.files, images.bytes, images.errors), (3, 10, 1));
+        let (_, music) = &stats.rules[1];
+        assert_eq!((music.files, music.bytes, music.errors), (0, 0, 0));
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::filters;
+use crate::tags;
+
+/// A sorting rule: files whose extension (and tags, if any are listed) match
+/// are moved into `folder` inside the output directory.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    /// Comma-separated list of file extensions, same format as the main
+    /// extensions field. Left empty, the rule matches every file.
+    pub extensions: String,
+    /// Comma-separated list of tags. Left empty, tags are ignored; otherwise
+    /// only files carrying at least one of them match.
+    pub tags: String,
+    /// Sub-folder of the output directory that matching files go into.
+    pub folder: String,
+}
+
+impl Rule {
+    pub fn new(name: &str, extensions: &str, folder: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: extensions.to_string(),
+            tags: String::new(),
+            folder: folder.to_string(),
+        }
+    }
+}
+
+/// A rule with its extensions already parsed, so matching thousands of
+/// files doesn't re-parse the extension string each time.
+pub struct CompiledRule {
+    extensions: Vec<String>,
+    tags: Vec<String>,
+    pub folder: String,
+}
+
+pub fn compile(rules: &[Rule], aliases: &[Vec<String>]) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .map(|rule| CompiledRule {
+            extensions: filters::parse_extensions(&rule.extensions, aliases),
+            tags: tags::parse_tags(&rule.tags),
+            folder: rule.folder.trim().to_string(),
+        })
+        .collect()
+}
+
+/// True if any rule filters by tags, in which case the tags have to be looked up.
+pub fn uses_tags(rules: &[CompiledRule]) -> bool {
+    rules.iter().any(|rule| !rule.tags.is_empty())
+}
+
+/// Returns the index of the first rule matching the file, if any.
+/// `file_tags` are the tags assigned to the file.
+pub fn first_match(
+    rules: &[CompiledRule],
+    file_path: &Path,
+    file_tags: &[String],
+) -> Option<usize> {
+    rules.iter().position(|rule| {
+        filters::matches_extension(&rule.extensions, file_path)
+            && (rule.tags.is_empty() || rule.tags.iter().any(|tag| file_tags.contains(tag)))
+    })
+}
+
+/// The built-in "Downloads cleanup" preset. Sorts a typical Downloads folder
+/// into a handful of category folders, with "Other" catching the rest.
+pub fn downloads_cleanup() -> Vec<Rule> {
+    vec![
+        Rule::new(
+            "Pictures",
+            "jpg, jpeg, png, gif, bmp, webp, heic, svg, tif, tiff",
+            "Pictures",
+        ),
+        Rule::new(
+            "Documents",
+            "pdf, doc, docx, xls, xlsx, ppt, pptx, odt, ods, odp, txt, rtf, csv, md, epub",
+            "Documents",
+        ),
+        Rule::new(
+            "Installers",
+            "exe, msi, dmg, pkg, deb, rpm, appimage, apk",
+            "Installers",
+        ),
+        Rule::new(
+            "Archives",
+            "zip, rar, 7z, tar, gz, bz2, xz, iso",
+            "Archives",
+        ),
+        Rule::new("Other", "", "Other"),
+    ]
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rusqlite::Connection;
+
+const DATABASE_FILE: &str = "history.sqlite3";
+
+/// A file next to the executable that turns on portable mode.
+const PORTABLE_FLAG_FILE: &str = "portable.flag";
+/// Folder next to the executable used in portable mode.
+const PORTABLE_DATA_DIR: &str = "data";
+
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the app's files next to the executable instead of in the user's
+/// data folder, e.g. to run it from a USB stick on several machines. Same as
+/// placing a `portable.flag` file there.
+pub fn enable_portable() {
+    PORTABLE.store(true, Ordering::Relaxed);
+}
+
+/// The data folder next to the executable, if running portable.
+fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    (PORTABLE.load(Ordering::Relaxed) || exe_dir.join(PORTABLE_FLAG_FILE).exists())
+        .then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+/// Folder where the app keeps its own files (job state, caches, history,
+/// profiles, ...). Created on first use.
+pub fn data_dir() -> io::Result<PathBuf> {
+    let dir = match portable_dir() {
+        Some(dir) => dir,
+        None => dirs::data_local_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no local data folder"))?
+            .join("move_files_gui"),
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Writes `contents` to a temporary file first and renames it into place, so
+/// a crash mid-write never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // Appended rather than replacing the extension, so files differing only
+    // in extension (job.json, job.progress) don't share a temporary file.
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Opens the app's SQLite database, shared by the history and the tags.
+pub fn open_database() -> Result<Connection, Box<dyn Error>> {
+    let conn = Connection::open(data_dir()?.join(DATABASE_FILE))?;
+    // Runs commit in short batches, so writers only wait briefly for each other.
+    conn.busy_timeout(Duration::from_secs(2))?;
+    // WAL lets the GUI read while a run is writing.
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = std::env::temp_dir().join("move_files_gui_storage_atomic");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("job.json");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+}
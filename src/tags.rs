@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use eframe::egui;
+use rusqlite::{params, Connection};
+
+use crate::logging::LogEntry;
+use crate::storage;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tags (
+        path TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (path, tag)
+    );
+";
+
+/// Parses a comma-separated list of tags. Tags are case-insensitive.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(|s| s.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The key a file's tags are stored under. Paths are resolved so the same
+/// file is found whether it was reached through a relative path, a symlink
+/// or the file picker.
+pub fn key(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Tags assigned to files, stored in the app's database.
+pub struct TagStore {
+    conn: Connection,
+}
+
+impl TagStore {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let conn = storage::open_database()?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Every tagged file with its tags, keyed by `key`.
+    pub fn all(&self) -> rusqlite::Result<HashMap<String, Vec<String>>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path, tag FROM tags ORDER BY tag")?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut all: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (path, tag) = row?;
+            all.entry(path).or_default().push(tag);
+        }
+        Ok(all)
+    }
+
+    /// Replaces the tags of a file.
+    pub fn set_tags(&mut self, path: &Path, tags: &[String]) -> rusqlite::Result<()> {
+        let key = key(path);
+        let transaction = self.conn.transaction()?;
+        transaction.execute("DELETE FROM tags WHERE path = ?1", [&key])?;
+        for tag in tags {
+            transaction.execute(
+                "INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)",
+                params![key, tag],
+            )?;
+        }
+        transaction.commit()
+    }
+
+    pub fn add_tag(&self, path: &Path, tag: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)",
+            params![key(path), tag],
+        )?;
+        Ok(())
+    }
+
+    /// Keeps a file's tags attached to it after it has been moved. `from_key`
+    /// is the key of the old location, taken before the move.
+    pub fn move_path(&self, from_key: &str, to: &Path) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE OR REPLACE tags SET path = ?1 WHERE path = ?2",
+            params![key(to), from_key],
+        )?;
+        Ok(())
+    }
+}
+
+/// A tag change made in the GUI.
+enum TagWrite {
+    /// Replaces the tags of a file.
+    Set(PathBuf, Vec<String>),
+    /// Adds a tag to the files picked by the user.
+    Add(Vec<PathBuf>, String),
+}
+
+/// Saves tag changes made in the GUI on a background thread, in the order
+/// they were made, so a database kept busy by another instance can't freeze
+/// the window.
+#[derive(Default)]
+pub struct TagWriter {
+    /// Changes for the thread, which is started by the first one.
+    writes: Option<mpsc::Sender<TagWrite>>,
+    /// What the thread has to report, for the log.
+    results: Option<mpsc::Receiver<LogEntry>>,
+}
+
+impl TagWriter {
+    pub fn set_tags(&mut self, ctx: &egui::Context, path: PathBuf, tags: Vec<String>) {
+        self.send(ctx, TagWrite::Set(path, tags));
+    }
+
+    pub fn add_tag(&mut self, ctx: &egui::Context, files: Vec<PathBuf>, tag: String) {
+        self.send(ctx, TagWrite::Add(files, tag));
+    }
+
+    fn send(&mut self, ctx: &egui::Context, write: TagWrite) {
+        let write = match &self.writes {
+            Some(writes) => match writes.send(write) {
+                Ok(()) => return,
+                // The thread panicked; another one is started below.
+                Err(mpsc::SendError(write)) => write,
+            },
+            None => write,
+        };
+        let (writes, pending) = mpsc::channel();
+        let (results, received) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || write_thread(pending, results, ctx));
+        let _ = writes.send(write);
+        self.writes = Some(writes);
+        self.results = Some(received);
+    }
+
+    /// Log entries about the changes saved, or not, since the last call.
+    pub fn results(&self) -> Vec<LogEntry> {
+        self.results.iter().flat_map(|rx| rx.try_iter()).collect()
+    }
+}
+
+/// This function runs in a background thread. It saves the changes sent by
+/// a `TagWriter` until the writer is dropped.
+fn write_thread(
+    writes: mpsc::Receiver<TagWrite>,
+    results: mpsc::Sender<LogEntry>,
+    ctx: egui::Context,
+) {
+    for write in writes {
+        let result = TagStore::open().and_then(|mut store| {
+            match &write {
+                TagWrite::Set(path, tags) => store.set_tags(path, tags)?,
+                TagWrite::Add(files, tag) => {
+                    for file in files {
+                        store.add_tag(file, tag)?;
+                    }
+                }
+            }
+            Ok(())
+        });
+        let entry = match (result, write) {
+            (Ok(()), TagWrite::Set(..)) => continue,
+            (Ok(()), TagWrite::Add(files, tag)) => {
+                LogEntry::info(format!("Tagged {} files with \"{}\".", files.len(), tag))
+            }
+            (Err(e), _) => LogEntry::error(format!("Could not save tags: {}", e)),
+        };
+        let _ = results.send(entry);
+        ctx.request_repaint();
+    }
+}
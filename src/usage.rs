@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use walkdir::WalkDir;
+
+use crate::filters;
+use crate::logging::LogEntry;
+use crate::mover::{Reporter, WorkerMessage};
+use crate::rules::{self, Rule};
+
+/// Files sharing an extension or category.
+pub struct Group {
+    pub name: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// How much of a folder each kind of file takes up, largest first.
+pub struct Usage {
+    pub folder: PathBuf,
+    pub total_bytes: u64,
+    pub by_extension: Vec<Group>,
+    pub by_category: Vec<Group>,
+}
+
+fn sorted(groups: HashMap<String, (usize, u64)>) -> Vec<Group> {
+    let mut groups: Vec<Group> = groups
+        .into_iter()
+        .map(|(name, (files, bytes))| Group { name, files, bytes })
+        .collect();
+    groups.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    groups
+}
+
+/// This function runs in a background thread. It adds up the files under
+/// `folder` by extension and by the category (rule) they fall into, and sends
+/// the totals back via the provided reporter.
+pub fn scan_thread(folder: PathBuf, categories: Vec<Rule>, aliases: String, reporter: Reporter) {
+    if !folder.is_dir() {
+        reporter.log(LogEntry::error(format!(
+            "{} is not a valid directory.",
+            folder.display()
+        )));
+        return;
+    }
+    let compiled = rules::compile(&categories, &filters::parse_aliases(&aliases));
+    let mut by_extension: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut by_category: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut total_bytes = 0;
+    for entry in WalkDir::new(&folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let extension = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let category = rules::first_match(&compiled, entry.path(), &[])
+            .map_or("(unmatched)", |index| categories[index].name.as_str());
+        for (groups, name) in [
+            (&mut by_extension, extension.as_str()),
+            (&mut by_category, category),
+        ] {
+            let group = groups.entry(name.to_string()).or_default();
+            group.0 += 1;
+            group.1 += size;
+        }
+        total_bytes += size;
+    }
+    reporter.send(WorkerMessage::Usage(Usage {
+        folder,
+        total_bytes,
+        by_extension: sorted(by_extension),
+        by_category: sorted(by_category),
+    }));
+}